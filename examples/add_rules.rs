@@ -16,11 +16,10 @@ use audit::{
 };
 
 #[tokio::main]
-async fn main() -> Result<(), String> {
-    let (connection, handle, _) =
-        new_connection().map_err(|e| format!("{e}"))?;
+async fn main() -> Result<(), Error> {
+    let (connection, handle, _) = new_connection()?;
     tokio::spawn(connection);
-    add_rules(handle).await.map_err(|e| format!("{e}"))
+    add_rules(handle).await
 }
 
 async fn add_rules(mut handle: Handle) -> Result<(), Error> {
@@ -7,11 +7,10 @@ use audit::{new_connection, Error, Handle};
 use futures::stream::TryStreamExt;
 
 #[tokio::main]
-async fn main() -> Result<(), String> {
-    let (connection, handle, _) =
-        new_connection().map_err(|e| format!("{e}"))?;
+async fn main() -> Result<(), Error> {
+    let (connection, handle, _) = new_connection()?;
     tokio::spawn(connection);
-    list_rules(handle).await.map_err(|e| format!("{e}"))
+    list_rules(handle).await
 }
 
 async fn list_rules(mut handle: Handle) -> Result<(), Error> {
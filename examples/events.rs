@@ -3,16 +3,15 @@
 //! This example opens a netlink socket, enables audit events, and prints the
 //! events that are being received.
 
-use audit::new_connection;
+use audit::{new_connection, Error};
 use futures::stream::StreamExt;
 
 #[tokio::main]
-async fn main() -> Result<(), String> {
-    let (connection, mut handle, mut messages) =
-        new_connection().map_err(|e| format!("{e}"))?;
+async fn main() -> Result<(), Error> {
+    let (connection, mut handle, mut messages) = new_connection()?;
 
     tokio::spawn(connection);
-    handle.enable_events().await.map_err(|e| format!("{e}"))?;
+    handle.enable_events().await?;
 
     env_logger::init();
     while let Some((msg, _)) = messages.next().await {
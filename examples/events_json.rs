@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: MIT
+
+//! This example enables audit events and prints them as newline-delimited
+//! JSON, one record per line, so that they can be piped to a log collector.
+
+use std::io::{self, Write};
+
+use audit::{new_connection, Error};
+use futures::stream::TryStreamExt;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let (connection, handle, messages) = new_connection()?;
+    let mut handle = handle.with_messages(messages);
+
+    tokio::spawn(connection);
+    handle.enable_events().await?;
+
+    let mut events = handle.events()?;
+    let stdout = io::stdout();
+    loop {
+        match events.try_next().await {
+            Ok(Some(event)) => {
+                let mut stdout = stdout.lock();
+                writeln!(stdout, "{}", event.to_json())?;
+                stdout.flush()?;
+            }
+            Ok(None) => return Ok(()),
+            // Keep streaming the next records
+            Err(e @ Error::Overrun) => eprintln!("{e}"),
+            Err(Error::InvalidEvent(e)) => eprintln!("Invalid record: {e}"),
+            Err(e) => return Err(e),
+        }
+    }
+}
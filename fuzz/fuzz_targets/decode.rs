@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: MIT
+
+//! Feed arbitrary bytes to the decoders of kernel messages: the netlink
+//! codec, the audit record parser and the typed records built on it, and
+//! the rule decoder. They must return errors on malformed input, never
+//! panic. Run with `cargo fuzz run decode`.
+
+#![no_main]
+
+use std::convert::TryFrom;
+
+use audit::{
+    packet::{rules::RuleMessage, AuditMessage},
+    AuditCodec, AuditEvent, ConfigChangeRecord, CwdRecord, ExecveRecord,
+    NetlinkPayload, PathRecord, ProctitleRecord, RuleMessageExt, SyscallRecord,
+};
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use netlink_proto::NetlinkMessageCodec;
+
+fn records(event: &AuditEvent) {
+    let _ = event.id();
+    let _ = PathRecord::from_record(event);
+    let _ = CwdRecord::from_record(event);
+    let _ = SyscallRecord::from_record(event);
+    let _ = ProctitleRecord::from_record(event);
+    let _ = ConfigChangeRecord::from_record(event);
+    let _ = ExecveRecord::from_records(std::iter::once(event));
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut buffer = BytesMut::from(data);
+    while let Ok(Some(message)) =
+        AuditCodec::decode::<AuditMessage>(&mut buffer)
+    {
+        if let NetlinkPayload::InnerMessage(message) = message.payload {
+            if let Ok(event) = AuditEvent::try_from(&message) {
+                records(&event);
+            }
+        }
+    }
+
+    if let Ok(mut event) = audit::parse_event(data) {
+        // The typed records only look at records of their own type
+        for record_type in [1300, 1302, 1305, 1307, 1309, 1327] {
+            event.record_type = record_type;
+            records(&event);
+        }
+    }
+
+    let _ = RuleMessage::from_netlink_bytes(data);
+});
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MIT
+
+use crate::packet::constants::{
+    __AUDIT_ARCH_LE, AUDIT_ARCH_AARCH64, AUDIT_ARCH_ARM, AUDIT_ARCH_ARMEB,
+    AUDIT_ARCH_I386, AUDIT_ARCH_PPC, AUDIT_ARCH_PPC64, AUDIT_ARCH_PPC64LE,
+    AUDIT_ARCH_S390, AUDIT_ARCH_S390X, AUDIT_ARCH_X86_64,
+};
+
+/// Flag of 64 bits architectures. netlink-packet-audit defines
+/// `__AUDIT_ARCH_64BIT` as `0x0800_0000` instead of the kernel's value.
+const AUDIT_ARCH_64BIT: u32 = 0x8000_0000;
+
+/// `EM_RISCV` machine type
+const EM_RISCV: u32 = 243;
+
+/// Audit architecture of 64 bits RISC-V, which `netlink-packet-audit` does
+/// not define
+pub const AUDIT_ARCH_RISCV64: u32 =
+    EM_RISCV | AUDIT_ARCH_64BIT | __AUDIT_ARCH_LE;
+
+/// Return the `AUDIT_ARCH_*` value of the architecture this crate is
+/// compiled for, which is the one to use in `RuleField::Arch` for native
+/// syscalls. Unsupported architectures return `0`.
+pub const fn current_arch() -> u32 {
+    if cfg!(target_arch = "x86_64") {
+        AUDIT_ARCH_X86_64
+    } else if cfg!(target_arch = "x86") {
+        AUDIT_ARCH_I386
+    } else if cfg!(target_arch = "aarch64") {
+        AUDIT_ARCH_AARCH64
+    } else if cfg!(all(target_arch = "arm", target_endian = "little")) {
+        AUDIT_ARCH_ARM
+    } else if cfg!(all(target_arch = "arm", target_endian = "big")) {
+        AUDIT_ARCH_ARMEB
+    } else if cfg!(target_arch = "riscv64") {
+        AUDIT_ARCH_RISCV64
+    } else if cfg!(all(target_arch = "powerpc64", target_endian = "little")) {
+        AUDIT_ARCH_PPC64LE
+    } else if cfg!(all(target_arch = "powerpc64", target_endian = "big")) {
+        AUDIT_ARCH_PPC64
+    } else if cfg!(target_arch = "powerpc") {
+        AUDIT_ARCH_PPC
+    } else if cfg!(target_arch = "s390x") {
+        AUDIT_ARCH_S390X
+    } else {
+        0
+    }
+}
+
+/// Return the architecture of `-F arch=b64` (`bits` = 64) or `-F arch=b32`
+/// (`bits` = 32), like auditctl: the native architecture for its own word
+/// size, or the 32 bits architecture of its compat syscalls. Return `None`
+/// if the native architecture has no such variant.
+///
+/// auditctl looks at the machine it runs on, while this looks at the one
+/// the crate is compiled for: they only differ for 32 bits binaries on 64
+/// bits kernels.
+pub(crate) fn arch_for_bits(bits: u32) -> Option<u32> {
+    let native = current_arch();
+    if native == 0 {
+        return None;
+    }
+    let native_bits = if native & AUDIT_ARCH_64BIT != 0 {
+        64
+    } else {
+        32
+    };
+    if bits == native_bits {
+        return Some(native);
+    }
+    match (native, bits) {
+        (AUDIT_ARCH_X86_64, 32) => Some(AUDIT_ARCH_I386),
+        (AUDIT_ARCH_AARCH64, 32) => Some(AUDIT_ARCH_ARM),
+        (AUDIT_ARCH_PPC64, 32) => Some(AUDIT_ARCH_PPC),
+        (AUDIT_ARCH_S390X, 32) => Some(AUDIT_ARCH_S390),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn riscv64_matches_the_kernel() {
+        // AUDIT_ARCH_RISCV64 in <linux/audit.h>
+        assert_eq!(AUDIT_ARCH_RISCV64, 0xC000_00F3);
+    }
+}
@@ -0,0 +1,271 @@
+// SPDX-License-Identifier: MIT
+
+//! Synchronous API, for programs that do not use an async runtime.
+//!
+//! [`Handle`] runs the netlink connection on an internal single threaded
+//! tokio runtime, which only makes progress while a method is being called.
+//! Unsolicited messages, such as audit events, are discarded: use the async
+//! API to receive them.
+//!
+//! ```no_run
+//! use audit::{blocking::Handle, RuleMessageExt};
+//!
+//! let mut handle = Handle::new().unwrap();
+//! for rule in handle.list_rules().unwrap() {
+//!     println!("{}", rule.display());
+//! }
+//! ```
+
+use std::{io, time::Duration};
+
+use futures::stream::TryStreamExt;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+    packet::{
+        rules::{RuleFlags, RuleMessage},
+        StatusMessage,
+    },
+    AuditMetrics, Error, FailureMode, Features, PidRegistration, RulesPlan,
+    SignalInfo, TtyStatus, UserRecord,
+};
+
+/// Blocking counterpart of [`crate::Handle`]. See the [module
+/// documentation](self).
+#[derive(Debug)]
+pub struct Handle {
+    runtime: Runtime,
+    handle: crate::Handle,
+}
+
+impl Handle {
+    /// Open a new netlink audit connection
+    pub fn new() -> io::Result<Self> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let handle = {
+            // The socket must be registered with the runtime's reactor
+            let _guard = runtime.enter();
+            let (connection, handle, _) = crate::new_connection()?;
+            runtime.spawn(connection);
+            handle
+        };
+        Ok(Handle { runtime, handle })
+    }
+
+    /// See [`crate::Handle::with_timeout`]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.handle = self.handle.with_timeout(timeout);
+        self
+    }
+
+    pub fn add_rule(&mut self, rule: RuleMessage) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.add_rule(rule))
+    }
+
+    pub fn add_rule_checked(
+        &mut self,
+        rule: RuleMessage,
+    ) -> Result<RuleMessage, Error> {
+        self.runtime.block_on(self.handle.add_rule_checked(rule))
+    }
+
+    pub fn add_rules(&mut self, rules: Vec<RuleMessage>) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.add_rules(rules))
+    }
+
+    pub fn del_rule(&mut self, rule: RuleMessage) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.del_rule(rule))
+    }
+
+    pub fn replace_rule(
+        &mut self,
+        old: RuleMessage,
+        new: RuleMessage,
+    ) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.replace_rule(old, new))
+    }
+
+    pub fn update_watch_perms(
+        &mut self,
+        path: &str,
+        perm: u32,
+    ) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.handle.update_watch_perms(path, perm))
+    }
+
+    pub fn list_rules(&mut self) -> Result<Vec<RuleMessage>, Error> {
+        self.runtime.block_on(self.handle.list_rules_vec())
+    }
+
+    pub fn list_rules_by_key(
+        &mut self,
+        key: &str,
+    ) -> Result<Vec<RuleMessage>, Error> {
+        self.runtime
+            .block_on(self.handle.list_rules_by_key(key).try_collect())
+    }
+
+    pub fn list_rules_by_filter(
+        &mut self,
+        flags: RuleFlags,
+    ) -> Result<Vec<RuleMessage>, Error> {
+        self.runtime
+            .block_on(self.handle.list_rules_by_filter(flags).try_collect())
+    }
+
+    pub fn apply_rules_plan(&mut self, plan: RulesPlan) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.apply_rules_plan(plan))
+    }
+
+    pub fn delete_all_rules(&mut self) -> Result<usize, Error> {
+        self.runtime.block_on(self.handle.delete_all_rules())
+    }
+
+    pub fn reload_rules(
+        &mut self,
+        rules: Vec<RuleMessage>,
+    ) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.reload_rules(rules))
+    }
+
+    pub fn ensure_rule(&mut self, rule: RuleMessage) -> Result<bool, Error> {
+        self.runtime.block_on(self.handle.ensure_rule(rule))
+    }
+
+    pub fn ensure_absent(&mut self, rule: &RuleMessage) -> Result<bool, Error> {
+        self.runtime.block_on(self.handle.ensure_absent(rule))
+    }
+
+    pub fn delete_rules_by_key(&mut self, key: &str) -> Result<usize, Error> {
+        self.runtime.block_on(self.handle.delete_rules_by_key(key))
+    }
+
+    pub fn trim(&mut self) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.trim())
+    }
+
+    pub fn make_equiv(&mut self, old: &str, new: &str) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.make_equiv(old, new))
+    }
+
+    pub fn enable_events(&mut self) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.enable_events())
+    }
+
+    pub fn disable_events(&mut self) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.disable_events())
+    }
+
+    pub fn set_status(&mut self, status: StatusMessage) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.set_status(status))
+    }
+
+    /// See `crate::Handle::lock_configuration`: this cannot be undone until
+    /// the next reboot.
+    pub fn lock_configuration(&mut self) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.lock_configuration())
+    }
+
+    pub fn set_enabled(&mut self, value: bool) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.set_enabled(value))
+    }
+
+    pub fn set_failure(&mut self, mode: FailureMode) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.set_failure(mode))
+    }
+
+    pub fn register_self(&mut self) -> Result<PidRegistration, Error> {
+        self.runtime.block_on(self.handle.register_self())
+    }
+
+    pub fn set_pid(&mut self, pid: u32) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.set_pid(pid))
+    }
+
+    pub fn set_rate_limit(
+        &mut self,
+        messages_per_second: u32,
+    ) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.handle.set_rate_limit(messages_per_second))
+    }
+
+    pub fn set_backlog_limit(&mut self, limit: u32) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.set_backlog_limit(limit))
+    }
+
+    pub fn set_backlog_wait_time(
+        &mut self,
+        wait_time: u32,
+    ) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.handle.set_backlog_wait_time(wait_time))
+    }
+
+    pub fn reset_lost(&mut self) -> Result<u32, Error> {
+        self.runtime.block_on(self.handle.reset_lost())
+    }
+
+    pub fn get_status(&mut self) -> Result<StatusMessage, Error> {
+        self.runtime.block_on(self.handle.get_status())
+    }
+
+    pub fn metrics(&mut self) -> Result<AuditMetrics, Error> {
+        self.runtime.block_on(self.handle.metrics())
+    }
+
+    pub fn ping(&mut self) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.ping())
+    }
+
+    pub fn get_version(&mut self) -> Result<u32, Error> {
+        self.runtime.block_on(self.handle.get_version())
+    }
+
+    pub fn get_pid(&mut self) -> Result<u32, Error> {
+        self.runtime.block_on(self.handle.get_pid())
+    }
+
+    pub fn get_features(&mut self) -> Result<Features, Error> {
+        self.runtime.block_on(self.handle.get_features())
+    }
+
+    pub fn set_feature(
+        &mut self,
+        feature: u32,
+        enable: bool,
+        lock: bool,
+    ) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.handle.set_feature(feature, enable, lock))
+    }
+
+    pub fn signal_info(&mut self) -> Result<SignalInfo, Error> {
+        self.runtime.block_on(self.handle.signal_info())
+    }
+
+    pub fn get_tty(&mut self) -> Result<TtyStatus, Error> {
+        self.runtime.block_on(self.handle.get_tty())
+    }
+
+    pub fn set_tty(&mut self, status: TtyStatus) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.set_tty(status))
+    }
+
+    pub fn send_user_message(
+        &mut self,
+        msg_type: u16,
+        text: &str,
+    ) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.handle.send_user_message(msg_type, text))
+    }
+
+    pub fn send_user_record(
+        &mut self,
+        record: UserRecord,
+    ) -> Result<(), Error> {
+        self.runtime.block_on(self.handle.send_user_record(record))
+    }
+}
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MIT
+
+use std::{fs, io};
+
+use crate::Error;
+
+/// Capabilities needed by audit requests
+#[derive(Copy, Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Capability {
+    /// `CAP_AUDIT_WRITE`, to send user space messages with
+    /// `Handle::send_user_message`
+    AuditWrite,
+    /// `CAP_AUDIT_CONTROL`, to change the status (including
+    /// `Handle::enable_events`) and the rules
+    AuditControl,
+    /// `CAP_AUDIT_READ`, to receive the events through the multicast group,
+    /// see [`new_connection_multicast`](crate::new_connection_multicast)
+    AuditRead,
+}
+
+impl Capability {
+    /// Return the name of the capability, such as `CAP_AUDIT_CONTROL`
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::AuditWrite => "CAP_AUDIT_WRITE",
+            Capability::AuditControl => "CAP_AUDIT_CONTROL",
+            Capability::AuditRead => "CAP_AUDIT_READ",
+        }
+    }
+
+    fn number(self) -> u32 {
+        match self {
+            Capability::AuditWrite => 29,
+            Capability::AuditControl => 30,
+            Capability::AuditRead => 37,
+        }
+    }
+}
+
+/// Check that the current thread has the given capabilities in its
+/// effective set, as read from `/proc/thread-self/status`, so that tools can
+/// fail early with a clear message rather than on their first request.
+///
+/// Return [`Error::MissingCapability`] for the first missing capability, or
+/// [`Error::Io`] if the capabilities cannot be read.
+///
+/// ```no_run
+/// use audit::{check_capabilities, Capability};
+///
+/// check_capabilities(&[Capability::AuditControl]).unwrap();
+/// ```
+pub fn check_capabilities(capabilities: &[Capability]) -> Result<(), Error> {
+    let effective = effective_capabilities()?;
+    for capability in capabilities {
+        if effective & (1 << capability.number()) == 0 {
+            return Err(Error::MissingCapability(capability.name()));
+        }
+    }
+    Ok(())
+}
+
+fn effective_capabilities() -> io::Result<u64> {
+    let status = fs::read_to_string("/proc/thread-self/status")?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "CapEff not found")
+        })
+}
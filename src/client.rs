@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: MIT
+
+use futures::stream::Stream;
+
+use crate::{new_connection, AuditEvent, Error, Handle, PidRegistration};
+
+/// A connection and its [`Handle`], for programs that do not need to drive
+/// the connection themselves.
+///
+/// The connection is spawned on the current tokio runtime. If
+/// [`AuditClient::events`] registered this process to receive the events,
+/// dropping the client unregisters it, see [`PidRegistration`].
+///
+/// ```no_run
+/// use audit::AuditClient;
+/// use futures::stream::TryStreamExt;
+///
+/// # async fn run() -> Result<(), audit::Error> {
+/// let mut client = AuditClient::new()?;
+/// println!("{:?}", client.handle().get_status().await?);
+///
+/// let mut events = client.events().await?;
+/// while let Some(event) = events.try_next().await? {
+///     println!("{:?}", event);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AuditClient {
+    handle: Handle,
+    registration: Option<PidRegistration>,
+}
+
+impl AuditClient {
+    /// Open a connection and spawn it with `tokio::spawn`, which panics
+    /// outside of a tokio runtime
+    pub fn new() -> Result<Self, Error> {
+        let (connection, handle, messages) = new_connection()?;
+        tokio::spawn(connection);
+        Ok(AuditClient {
+            handle: handle.with_messages(messages),
+            registration: None,
+        })
+    }
+
+    pub fn handle(&mut self) -> &mut Handle {
+        &mut self.handle
+    }
+
+    /// Register this process to receive the events with
+    /// `Handle::enable_events`, and return the stream of `Handle::events`.
+    /// This can only be called once: later calls fail with
+    /// [`Error::EventsUnavailable`], and leave the registration alone.
+    pub async fn events(
+        &mut self,
+    ) -> Result<impl Stream<Item = Result<AuditEvent, Error>>, Error> {
+        // Fail before registering again: replacing the registration would
+        // unregister the process
+        if self.registration.is_some() || !self.handle.has_messages() {
+            return Err(Error::EventsUnavailable);
+        }
+        self.handle.enable_events().await?;
+        // enable_events does not report the previous pid
+        self.registration = Some(PidRegistration::new(0));
+        self.handle.events()
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::testing::MockKernel;
+
+    #[tokio::test]
+    async fn events_twice() {
+        let kernel = MockKernel::new(|request| vec![MockKernel::ack(request)]);
+        let (connection, handle, messages) = kernel.connect().unwrap();
+        tokio::spawn(connection);
+        let mut client = AuditClient {
+            handle: handle.with_messages(messages),
+            registration: None,
+        };
+
+        assert!(client.events().await.is_ok());
+        let requests = kernel.requests().len();
+        assert!(matches!(
+            client.events().await,
+            Err(Error::EventsUnavailable)
+        ));
+        assert_eq!(kernel.requests().len(), requests);
+
+        // Dropping the registration would unregister the process that
+        // receives the events of the host
+        client.registration.take().unwrap().keep();
+    }
+}
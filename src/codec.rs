@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MIT
+
+use std::{fmt::Debug, io};
+
+use bytes::BytesMut;
+use netlink_packet_core::{
+    NetlinkBuffer, NetlinkDeserializable, NetlinkMessage, NetlinkSerializable,
+    NETLINK_HEADER_LEN, NLMSG_DONE,
+};
+use netlink_proto::NetlinkMessageCodec;
+
+use crate::packet::NetlinkAuditCodec;
+
+/// Length of the error code that starts the payload of `NLMSG_DONE`
+const DONE_CODE_LEN: usize = 4;
+
+/// The codec of the audit connections.
+///
+/// It works around the same kernel bugs as [`NetlinkAuditCodec`], plus
+/// one more: the kernel ends the rule dumps with an `NLMSG_DONE` message
+/// that has no payload, while `netlink-packet-core` expects the error
+/// code there. [`NetlinkAuditCodec`] alone drops such a message, so the
+/// dump never completes. This codec adds a zero code before decoding it.
+#[non_exhaustive]
+pub struct AuditCodec {
+    // we don't need an instance of this, just the type
+    _private: (),
+}
+
+impl NetlinkMessageCodec for AuditCodec {
+    fn decode<T>(src: &mut BytesMut) -> io::Result<Option<NetlinkMessage<T>>>
+    where
+        T: NetlinkDeserializable + Debug,
+    {
+        pad_empty_done(src);
+        NetlinkAuditCodec::decode(src)
+    }
+
+    fn encode<T>(msg: NetlinkMessage<T>, buf: &mut BytesMut) -> io::Result<()>
+    where
+        T: Debug + NetlinkSerializable,
+    {
+        NetlinkAuditCodec::encode(msg, buf)
+    }
+}
+
+fn pad_empty_done(src: &mut BytesMut) {
+    match NetlinkBuffer::new_checked(src.as_ref()) {
+        Ok(buf)
+            if buf.message_type() == NLMSG_DONE
+                && buf.length() as usize == NETLINK_HEADER_LEN => {}
+        _ => return,
+    }
+    let rest = src.split_off(NETLINK_HEADER_LEN);
+    src.extend_from_slice(&[0; DONE_CODE_LEN]);
+    NetlinkBuffer::new(src.as_mut())
+        .set_length((NETLINK_HEADER_LEN + DONE_CODE_LEN) as u32);
+    src.unsplit(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use netlink_packet_core::{
+        DoneMessage, NetlinkHeader, NetlinkPayload, NLM_F_MULTIPART,
+    };
+
+    use super::*;
+    use crate::packet::AuditMessage;
+
+    // The end of an empty rule dump, as sent by the kernel
+    const EMPTY_DONE: [u8; 16] = [
+        0x10, 0x00, 0x00, 0x00, // length
+        0x03, 0x00, // NLMSG_DONE
+        0x02, 0x00, // NLM_F_MULTI
+        0x2a, 0x00, 0x00, 0x00, // sequence number
+        0x00, 0x00, 0x00, 0x00, // port
+    ];
+
+    fn decode(bytes: &[u8]) -> Option<NetlinkMessage<AuditMessage>> {
+        let mut src = BytesMut::from(bytes);
+        AuditCodec::decode(&mut src).unwrap()
+    }
+
+    #[test]
+    fn decode_empty_done() {
+        let message = decode(&EMPTY_DONE).unwrap();
+        assert_eq!(message.header.sequence_number, 42);
+        assert_eq!(message.header.flags, NLM_F_MULTIPART);
+        assert_eq!(
+            message.payload,
+            NetlinkPayload::Done(DoneMessage::default())
+        );
+    }
+
+    #[test]
+    fn decode_done() {
+        let mut done = NetlinkMessage::<AuditMessage>::new(
+            NetlinkHeader::default(),
+            NetlinkPayload::Done(DoneMessage::default()),
+        );
+        done.header.sequence_number = 42;
+        done.finalize();
+        let mut bytes = vec![0; done.buffer_len()];
+        done.serialize(&mut bytes);
+        assert_eq!(decode(&bytes), Some(done));
+    }
+}
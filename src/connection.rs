@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: MIT
+
+use std::{convert::TryFrom, io, os::unix::io::AsRawFd};
+
+use futures::channel::mpsc::UnboundedReceiver;
+
+use crate::{
+    new_connection_with_socket, packet, proto, sys, AuditCodec, Handle,
+    NetlinkMessage,
+};
+
+/// Builder for a netlink audit connection with a customized socket.
+///
+/// ```no_run
+/// use audit::ConnectionBuilder;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let (connection, handle, messages) = ConnectionBuilder::new()
+///     .rcvbuf(16 * 1024 * 1024)
+///     .build()?;
+/// tokio::spawn(connection);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionBuilder {
+    rcvbuf: Option<usize>,
+    force_rcvbuf: bool,
+    multicast: bool,
+    no_enobufs: bool,
+}
+
+impl ConnectionBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the size of the socket receive buffer (`SO_RCVBUF`). A bigger
+    /// buffer avoids losing events when they are received faster than they
+    /// are processed. The kernel caps the size to `net.core.rmem_max`.
+    pub fn rcvbuf(mut self, size: usize) -> Self {
+        self.rcvbuf = Some(size);
+        self.force_rcvbuf = false;
+        self
+    }
+
+    /// Same as [`ConnectionBuilder::rcvbuf`], but use `SO_RCVBUFFORCE`,
+    /// which is not capped by `net.core.rmem_max`. This requires the
+    /// `CAP_NET_ADMIN` capability.
+    pub fn force_rcvbuf(mut self, size: usize) -> Self {
+        self.rcvbuf = Some(size);
+        self.force_rcvbuf = true;
+        self
+    }
+
+    /// Subscribe to the `AUDIT_NLGRP_READLOG` multicast group. See
+    /// [`new_connection_multicast`](crate::new_connection_multicast).
+    pub fn multicast(mut self, multicast: bool) -> Self {
+        self.multicast = multicast;
+        self
+    }
+
+    /// Set `NETLINK_NO_ENOBUFS`. It is unset by default, so that the events
+    /// stream reports [`Error::Overrun`](crate::Error::Overrun) when the
+    /// socket buffer overflows. Setting it hides the overruns: only do so if
+    /// the `lost` counter of `Handle::get_status` is monitored instead.
+    pub fn no_enobufs(mut self, no_enobufs: bool) -> Self {
+        self.no_enobufs = no_enobufs;
+        self
+    }
+
+    #[allow(clippy::type_complexity)]
+    #[cfg(feature = "tokio_socket")]
+    pub fn build(
+        self,
+    ) -> io::Result<(
+        proto::Connection<packet::AuditMessage, sys::TokioSocket, AuditCodec>,
+        Handle,
+        UnboundedReceiver<(
+            NetlinkMessage<packet::AuditMessage>,
+            sys::SocketAddr,
+        )>,
+    )> {
+        self.build_with_socket()
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn build_with_socket<S>(
+        self,
+    ) -> io::Result<(
+        proto::Connection<packet::AuditMessage, S, AuditCodec>,
+        Handle,
+        UnboundedReceiver<(
+            NetlinkMessage<packet::AuditMessage>,
+            sys::SocketAddr,
+        )>,
+    )>
+    where
+        S: sys::AsyncSocket,
+    {
+        let (mut conn, handle, messages) = new_connection_with_socket::<S>()?;
+        let socket = conn.socket_mut().socket_mut();
+        match self.rcvbuf {
+            Some(size) if self.force_rcvbuf => force_rcvbuf(socket, size)?,
+            Some(size) => socket.set_rx_buf_sz(size)?,
+            None => {}
+        }
+        socket.set_no_enobufs(self.no_enobufs)?;
+        if self.multicast {
+            socket.add_membership(packet::constants::AUDIT_NLGRP_READLOG)?;
+        }
+        Ok((conn, handle, messages))
+    }
+}
+
+fn force_rcvbuf(socket: &impl AsRawFd, size: usize) -> io::Result<()> {
+    let size = libc::c_int::try_from(size)
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    // SAFETY: the option value is a valid c_int, whose size is passed along
+    let res = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_RCVBUFFORCE,
+            &size as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -1,18 +1,352 @@
 // SPDX-License-Identifier: MIT
 
+use std::{fmt, io, sync::Arc};
+
 use thiserror::Error;
 
-use crate::packet::AuditMessage;
+use crate::{
+    packet::AuditMessage, proto, BuildError, ParseError, ParseRuleError,
+};
 use netlink_packet_core::{ErrorMessage, NetlinkMessage};
 
+/// The errors of this crate. The underlying [`io::Error`], [`ParseError`]
+/// or rule error is available through [`std::error::Error::source`], and
+/// is not repeated in the message, so that error reporters walking the
+/// sources print it once. `?` converts [`io::Error`], [`ErrorMessage`],
+/// [`ParseError`], [`ParseRuleError`] and [`BuildError`] into this type.
 #[derive(Clone, Eq, PartialEq, Debug, Error)]
 pub enum Error {
     #[error("Received an unexpected message {0:?}")]
     UnexpectedMessage(NetlinkMessage<AuditMessage>),
 
-    #[error("Received a netlink error message {0:?}")]
+    /// The kernel replied with an error that has no dedicated variant. The
+    /// message is displayed with the `strerror` text of its errno, see
+    /// [`Error::errno`].
+    #[error("Received a netlink error message: {}", errno_text(.0))]
     NetlinkError(ErrorMessage),
 
+    /// The kernel replied with `EPERM` or `EACCES`, usually because the
+    /// process lacks the `CAP_AUDIT_CONTROL` or `CAP_AUDIT_WRITE` capability
+    #[error("Permission denied")]
+    PermissionDenied,
+
+    /// The kernel replied with `EEXIST` when adding a rule
+    #[error("Rule already exists")]
+    RuleExists,
+
+    /// The kernel acknowledged a rule of `Handle::add_rule_checked`, but
+    /// did not list it
+    #[error("Rule was added but is not listed by the kernel")]
+    RuleNotListed,
+
+    /// `Handle::register_self` was called while auditing is disabled
+    #[error("Auditing is disabled")]
+    AuditDisabled,
+
+    /// Another process, whose pid is given, is registered to receive the
+    /// events. The kernel replied with `EEXIST`.
+    #[error("Process {0} is already registered to receive the events")]
+    PidRegistered(u32),
+
+    /// The kernel replied with `ENOSPC`
+    #[error("Audit backlog is full")]
+    BacklogFull,
+
+    /// The process lacks a capability, see
+    /// [`check_capabilities`](crate::check_capabilities)
+    #[error("Missing capability {0}")]
+    MissingCapability(&'static str),
+
+    /// The kernel did not send any reply
     #[error("Request failed")]
     RequestFailed,
+
+    /// The connection future was dropped, or ended because of a socket
+    /// error
+    #[error("The netlink connection is closed")]
+    ConnectionClosed,
+
+    #[error("Socket error")]
+    Io(#[source] IoError),
+
+    #[error("Timed out waiting for the kernel reply")]
+    Timeout,
+
+    #[error("Invalid failure mode {0}")]
+    InvalidFailureMode(u32),
+
+    #[error("Invalid audit feature {0}")]
+    InvalidFeature(u32),
+
+    #[error("Failed to parse audit record")]
+    InvalidEvent(#[source] ParseError),
+
+    /// The socket buffer was full (`ENOBUFS`) and some audit records were
+    /// dropped.
+    ///
+    /// The number of records is not known. The kernel keeps the records it
+    /// could not deliver in its backlog and retries, so they are only lost
+    /// once the backlog overflows: the `lost` counter of
+    /// `Handle::get_status` tells how many were. Gaps in the serial numbers
+    /// are not reliable, since the records of concurrent events arrive out
+    /// of order, and filtered events use serial numbers too.
+    #[error("Socket buffer overrun, audit records were lost")]
+    Overrun,
+
+    /// `Handle::events` was called without a messages receiver, or more
+    /// than once
+    #[error("Events stream is not available")]
+    EventsUnavailable,
+
+    #[error("Invalid rule: {0}")]
+    InvalidRule(String),
+
+    /// A rule could not be parsed by `parse_rule`
+    #[error("Failed to parse rule")]
+    ParseRule(#[source] ParseRuleError),
+
+    /// A rule could not be built by `RuleBuilder`
+    #[error("Failed to build rule")]
+    BuildRule(#[source] BuildError),
+
+    /// A rule of `Handle::add_rules` failed
+    #[error("Rule {index} failed")]
+    RuleFailed {
+        index: usize,
+        #[source]
+        error: Box<Error>,
+    },
+
+    /// A request failed, and so did undoing the changes made before it, for
+    /// instance restoring the previous rules in `Handle::reload_rules`.
+    /// `error` caused the rollback, and is the source of this error. The
+    /// rules may be left halfway: list them to check what is in place.
+    #[error("Rollback failed: {rollback}")]
+    RollbackFailed {
+        #[source]
+        error: Box<Error>,
+        rollback: Box<Error>,
+    },
+
+    #[error("Invalid user message type {0}")]
+    InvalidUserMessageType(u16),
+
+    /// The message payload cannot be carried by `AuditMessage::Other`, which
+    /// only supports valid UTF-8
+    #[error("Payload of message type {0} cannot be encoded")]
+    UnsupportedPayload(u16),
+}
+
+impl Error {
+    /// Return the errno the kernel replied with, if the error comes from a
+    /// netlink error message. `Error::PermissionDenied` returns `EPERM`,
+    /// although the kernel may have replied with `EACCES`.
+    ///
+    /// ```
+    /// use audit::Error;
+    ///
+    /// assert_eq!(Error::RuleExists.errno(), Some(libc::EEXIST));
+    /// assert_eq!(Error::Timeout.errno(), None);
+    /// ```
+    pub fn errno(&self) -> Option<i32> {
+        match self {
+            Error::NetlinkError(err_msg) => {
+                err_msg.code.map(|code| -code.get())
+            }
+            Error::PermissionDenied => Some(libc::EPERM),
+            Error::RuleExists | Error::PidRegistered(_) => Some(libc::EEXIST),
+            Error::BacklogFull => Some(libc::ENOSPC),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Map `EEXIST`, the reply of the kernel to a rule that is already
+    /// loaded, to `Error::RuleExists`
+    pub(crate) fn for_rule(self) -> Self {
+        match self.errno() {
+            Some(libc::EEXIST) => Error::RuleExists,
+            _ => self,
+        }
+    }
+}
+
+fn errno_text(err_msg: &ErrorMessage) -> String {
+    match err_msg.code {
+        Some(code) => io::Error::from_raw_os_error(-code.get()).to_string(),
+        None => "success".to_string(),
+    }
+}
+
+impl From<ErrorMessage> for Error {
+    /// Map the errno of a netlink error to the matching variant, falling
+    /// back to `Error::NetlinkError`. `EEXIST` is not mapped, since the
+    /// kernel replies with it both when a rule exists and when another
+    /// process is registered: the requests adding rules map it to
+    /// `Error::RuleExists`.
+    fn from(err_msg: ErrorMessage) -> Self {
+        match err_msg.code.map(|code| -code.get()) {
+            Some(libc::EPERM) | Some(libc::EACCES) => Error::PermissionDenied,
+            Some(libc::ENOSPC) => Error::BacklogFull,
+            _ => Error::NetlinkError(err_msg),
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::InvalidEvent(err)
+    }
+}
+
+impl From<ParseRuleError> for Error {
+    fn from(err: ParseRuleError) -> Self {
+        Error::ParseRule(err)
+    }
+}
+
+impl From<BuildError> for Error {
+    fn from(err: BuildError) -> Self {
+        Error::BuildRule(err)
+    }
+}
+
+impl From<proto::Error<AuditMessage>> for Error {
+    fn from(err: proto::Error<AuditMessage>) -> Self {
+        match err {
+            proto::Error::ConnectionClosed => Error::ConnectionClosed,
+            proto::Error::NetlinkError(message) => {
+                Error::UnexpectedMessage(message)
+            }
+            proto::Error::SocketIo(err) => Error::from(err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(IoError(Arc::new(err)))
+    }
+}
+
+/// An [`io::Error`] that can be cloned and compared, as
+/// [`Error`](enum@Error) requires. Errors are equal if they have the same
+/// kind, errno and message.
+#[derive(Debug, Clone)]
+pub struct IoError(Arc<io::Error>);
+
+impl IoError {
+    pub fn get_ref(&self) -> &io::Error {
+        &self.0
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.kind() == other.0.kind()
+            && self.0.raw_os_error() == other.0.raw_os_error()
+            && self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Eq for IoError {}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for IoError {
+    // The io::Error itself is displayed by IoError, so report its source
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+    use crate::parse_rule;
+
+    #[test]
+    fn rule_errors_keep_their_source() {
+        let parse = || -> Result<(), Error> {
+            parse_rule("-a always,exit -S nosuchsyscall")?;
+            Ok(())
+        };
+        let err = parse().unwrap_err();
+        assert_eq!(
+            err,
+            Error::ParseRule(ParseRuleError::UnknownSyscall(
+                "nosuchsyscall".into()
+            ))
+        );
+        assert_eq!(err.to_string(), "Failed to parse rule");
+        assert_eq!(
+            err.source().unwrap().to_string(),
+            "Unknown syscall \"nosuchsyscall\""
+        );
+    }
+
+    /// Return the messages of `err` and of all its sources
+    fn chain(err: &dyn std::error::Error) -> Vec<String> {
+        let mut messages = vec![err.to_string()];
+        let mut source = err.source();
+        while let Some(err) = source {
+            messages.push(err.to_string());
+            source = err.source();
+        }
+        messages
+    }
+
+    fn assert_chain(err: Error, expected: &[&str]) {
+        let messages = chain(&err);
+        assert_eq!(messages, expected);
+        for (i, message) in messages.iter().enumerate() {
+            for source in &messages[i + 1..] {
+                assert!(!message.contains(source.as_str()), "{:?}", messages);
+            }
+        }
+    }
+
+    #[test]
+    fn sources_are_not_repeated() {
+        let enobufs = || io::Error::from_raw_os_error(libc::ENOBUFS);
+        let enobufs_text = enobufs().to_string();
+        assert!(enobufs_text.starts_with("No buffer space available"));
+        assert_chain(Error::from(enobufs()), &["Socket error", &enobufs_text]);
+
+        // The source of an io::Error is the source of its inner error
+        let parse = ParseError::InvalidTimestamp("x".into());
+        let parse_text = parse.to_string();
+        let io = io::Error::new(io::ErrorKind::InvalidData, parse);
+        assert_chain(Error::from(io), &["Socket error", &parse_text]);
+
+        assert_chain(
+            Error::RuleFailed {
+                index: 2,
+                error: Box::new(Error::RuleExists),
+            },
+            &["Rule 2 failed", "Rule already exists"],
+        );
+        assert_chain(
+            Error::RollbackFailed {
+                error: Box::new(Error::RuleFailed {
+                    index: 0,
+                    error: Box::new(Error::from(enobufs())),
+                }),
+                rollback: Box::new(Error::PermissionDenied),
+            },
+            &[
+                "Rollback failed: Permission denied",
+                "Rule 0 failed",
+                "Socket error",
+                &enobufs_text,
+            ],
+        );
+    }
 }
@@ -0,0 +1,172 @@
+// SPDX-License-Identifier: MIT
+
+use crate::packet::constants::{AUDIT_ARCH_AARCH64, AUDIT_ARCH_X86_64};
+
+// `open` flags shared by x86_64 and aarch64. The access mode, in the two
+// lowest bits, is rendered separately.
+const OPEN_FLAGS: &[(u64, &str)] = &[
+    (0o100, "O_CREAT"),
+    (0o200, "O_EXCL"),
+    (0o400, "O_NOCTTY"),
+    (0o1000, "O_TRUNC"),
+    (0o2000, "O_APPEND"),
+    (0o4000, "O_NONBLOCK"),
+    (0o10000, "O_DSYNC"),
+    (0o20000, "O_ASYNC"),
+    (0o1000000, "O_NOATIME"),
+    (0o2000000, "O_CLOEXEC"),
+    (0o4000000, "O_SYNC"),
+    (0o10000000, "O_PATH"),
+    (0o20000000, "O_TMPFILE"),
+];
+
+const X86_64_OPEN_FLAGS: &[(u64, &str)] = &[
+    (0o40000, "O_DIRECT"),
+    (0o100000, "O_LARGEFILE"),
+    (0o200000, "O_DIRECTORY"),
+    (0o400000, "O_NOFOLLOW"),
+];
+
+const AARCH64_OPEN_FLAGS: &[(u64, &str)] = &[
+    (0o40000, "O_DIRECTORY"),
+    (0o100000, "O_NOFOLLOW"),
+    (0o200000, "O_DIRECT"),
+    (0o400000, "O_LARGEFILE"),
+];
+
+const PROT_FLAGS: &[(u64, &str)] =
+    &[(0x1, "PROT_READ"), (0x2, "PROT_WRITE"), (0x4, "PROT_EXEC")];
+
+// `mmap` flags, without the mapping type in the lowest four bits
+const MAP_FLAGS: &[(u64, &str)] = &[
+    (0x10, "MAP_FIXED"),
+    (0x20, "MAP_ANONYMOUS"),
+    (0x100, "MAP_GROWSDOWN"),
+    (0x800, "MAP_DENYWRITE"),
+    (0x1000, "MAP_EXECUTABLE"),
+    (0x2000, "MAP_LOCKED"),
+    (0x4000, "MAP_NORESERVE"),
+    (0x8000, "MAP_POPULATE"),
+    (0x10000, "MAP_NONBLOCK"),
+    (0x20000, "MAP_STACK"),
+    (0x40000, "MAP_HUGETLB"),
+    (0x80000, "MAP_SYNC"),
+    (0x100000, "MAP_FIXED_NOREPLACE"),
+];
+
+const X86_64_MAP_FLAGS: &[(u64, &str)] = &[(0x40, "MAP_32BIT")];
+
+const O_CREAT: u64 = 0o100;
+const O_TMPFILE: u64 = 0o20000000;
+const AT_FDCWD: u32 = -100i32 as u32;
+
+/// Render the arguments of `syscall` as `ausearch -i` does. Arguments that
+/// are not interpreted, such as pointers, are kept in hexadecimal.
+pub(crate) fn decode_args(
+    arch: u32,
+    syscall: &str,
+    args: &[u64; 4],
+) -> Option<[String; 4]> {
+    let (open_flags, map_flags) = match arch {
+        AUDIT_ARCH_X86_64 => (X86_64_OPEN_FLAGS, X86_64_MAP_FLAGS),
+        AUDIT_ARCH_AARCH64 => (AARCH64_OPEN_FLAGS, &[][..]),
+        _ => return None,
+    };
+    let mut decoded = args.map(|arg| format!("{arg:x}"));
+    match syscall {
+        "open" => {
+            decoded[1] = open_flags_str(args[1], open_flags);
+            if args[1] & (O_CREAT | O_TMPFILE) != 0 {
+                decoded[2] = format!("{:04o}", args[2]);
+            }
+        }
+        "openat" => {
+            decoded[0] = dirfd_str(args[0]);
+            decoded[2] = open_flags_str(args[2], open_flags);
+            if args[2] & (O_CREAT | O_TMPFILE) != 0 {
+                decoded[3] = format!("{:04o}", args[3]);
+            }
+        }
+        "mmap" => {
+            decoded[2] = flags_str(args[2], PROT_FLAGS, "PROT_NONE");
+            decoded[3] = map_flags_str(args[3], map_flags);
+        }
+        "mprotect" => {
+            decoded[2] = flags_str(args[2], PROT_FLAGS, "PROT_NONE");
+        }
+        _ => return None,
+    }
+    Some(decoded)
+}
+
+fn dirfd_str(fd: u64) -> String {
+    // dirfd is an int, printed without sign extension
+    if fd as u32 == AT_FDCWD {
+        "AT_FDCWD".to_string()
+    } else {
+        format!("{fd:x}")
+    }
+}
+
+fn open_flags_str(flags: u64, arch_flags: &[(u64, &str)]) -> String {
+    let mode = match flags & 0o3 {
+        0 => "O_RDONLY",
+        1 => "O_WRONLY",
+        2 => "O_RDWR",
+        _ => "O_ACCMODE",
+    };
+    let mut s = mode.to_string();
+    let rest = push_flags(&mut s, flags & !0o3, &[OPEN_FLAGS, arch_flags]);
+    push_rest(&mut s, rest);
+    s
+}
+
+fn map_flags_str(flags: u64, arch_flags: &[(u64, &str)]) -> String {
+    let mut s = match flags & 0xf {
+        0x1 => "MAP_SHARED".to_string(),
+        0x2 => "MAP_PRIVATE".to_string(),
+        0x3 => "MAP_SHARED_VALIDATE".to_string(),
+        kind => format!("{kind:x}"),
+    };
+    let rest = push_flags(&mut s, flags & !0xf, &[MAP_FLAGS, arch_flags]);
+    push_rest(&mut s, rest);
+    s
+}
+
+fn flags_str(flags: u64, names: &[(u64, &str)], none: &str) -> String {
+    let mut s = String::new();
+    let rest = push_flags(&mut s, flags, &[names]);
+    push_rest(&mut s, rest);
+    if s.is_empty() {
+        s.push_str(none);
+    }
+    s
+}
+
+/// Append the names of the set flags, separated by `|`, and return the bits
+/// that have no name
+fn push_flags(
+    s: &mut String,
+    mut flags: u64,
+    tables: &[&[(u64, &str)]],
+) -> u64 {
+    for (bit, name) in tables.iter().flat_map(|table| table.iter()) {
+        if flags & bit == *bit {
+            if !s.is_empty() {
+                s.push('|');
+            }
+            s.push_str(name);
+            flags &= !bit;
+        }
+    }
+    flags
+}
+
+fn push_rest(s: &mut String, rest: u64) {
+    if rest != 0 {
+        if !s.is_empty() {
+            s.push('|');
+        }
+        s.push_str(&format!("0x{rest:x}"));
+    }
+}
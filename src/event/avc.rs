@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    event::{path::number, AuditEvent, FieldValue},
+    packet::constants::{AUDIT_AVC, AUDIT_USER_AVC},
+};
+
+/// Decision of an SELinux access vector check
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AvcDecision {
+    Denied,
+    /// Access was granted, and logged because of an `auditallow` rule
+    Granted,
+    /// Any other decision
+    Other(String),
+}
+
+impl From<&str> for AvcDecision {
+    fn from(decision: &str) -> Self {
+        match decision {
+            "denied" => AvcDecision::Denied,
+            "granted" => AvcDecision::Granted,
+            decision => AvcDecision::Other(decision.to_string()),
+        }
+    }
+}
+
+/// An SELinux access decision, from an `AUDIT_AVC` record logged by the
+/// kernel, or an `AUDIT_USER_AVC` record logged by an object manager such
+/// as dbus or systemd:
+/// `avc:  denied  { read } for  pid=42 comm="cat" name="shadow"
+/// scontext=... tcontext=... tclass=file permissive=0`.
+///
+/// The object fields, such as `name`, `path` or `dev`, depend on the class
+/// of the target: those that are not listed here are only available through
+/// the record.
+///
+/// ```
+/// use audit::{AvcDecision, AvcRecord};
+///
+/// let mut record = audit::parse_event(
+///     b"type=AVC msg=audit(1699999999.123:4567): avc:  denied  \
+///       { read write } for  pid=42 comm=\"cat\" name=\"shadow\" \
+///       scontext=system_u:system_r:httpd_t:s0 \
+///       tcontext=system_u:object_r:shadow_t:s0 tclass=file permissive=0",
+/// )
+/// .unwrap();
+/// // parse_event leaves the type to the netlink header
+/// record.record_type = audit::packet::constants::AUDIT_AVC;
+/// let avc = AvcRecord::from_record(&record).unwrap();
+/// assert_eq!(avc.decision, AvcDecision::Denied);
+/// assert_eq!(avc.permissions, ["read", "write"]);
+/// assert_eq!(avc.tclass.as_deref(), Some("file"));
+/// assert_eq!(avc.permissive, Some(false));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AvcRecord {
+    pub decision: AvcDecision,
+    /// The permissions that were checked, such as `read` or `write`
+    pub permissions: Vec<String>,
+    pub pid: Option<u32>,
+    /// Command name of the process. Invalid UTF-8 sequences are replaced
+    /// with `U+FFFD`.
+    pub comm: Option<String>,
+    /// Security context of the process (source)
+    pub scontext: Option<String>,
+    /// Security context of the object (target)
+    pub tcontext: Option<String>,
+    /// Class of the object, such as `file` or `tcp_socket`
+    pub tclass: Option<String>,
+    /// Name of the object, for files
+    pub name: Option<String>,
+    /// Path of the object, for files
+    pub path: Option<String>,
+    /// Whether the denial was only logged, because the domain or the whole
+    /// system is permissive
+    pub permissive: Option<bool>,
+}
+
+impl AvcRecord {
+    /// Return `None` if the record is not an `AUDIT_AVC` or
+    /// `AUDIT_USER_AVC` record with an access decision. Other AVC records,
+    /// such as policy loads, have no decision.
+    pub fn from_record(record: &AuditEvent) -> Option<Self> {
+        if record.record_type != AUDIT_AVC
+            && record.record_type != AUDIT_USER_AVC
+        {
+            return None;
+        }
+        let string = |name: &str| {
+            record
+                .field(name)
+                .and_then(FieldValue::as_bytes)
+                .map(|value| String::from_utf8_lossy(value).into_owned())
+        };
+
+        Some(AvcRecord {
+            decision: AvcDecision::from(record.field("seresult")?.as_str()?),
+            permissions: record
+                .field("seperms")?
+                .as_str()?
+                .split(',')
+                .filter(|perm| !perm.is_empty())
+                .map(str::to_string)
+                .collect(),
+            pid: number(record.field("pid")),
+            comm: string("comm"),
+            scontext: string("scontext"),
+            tcontext: string("tcontext"),
+            tclass: string("tclass"),
+            name: string("name"),
+            path: string("path"),
+            permissive: match number::<u32>(record.field("permissive")) {
+                Some(0) => Some(false),
+                Some(1) => Some(true),
+                _ => None,
+            },
+        })
+    }
+}
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use futures::{
+    channel::mpsc::{channel, Receiver},
+    stream::{Stream, StreamExt},
+};
+
+/// Number of events dropped because the channel of `Handle::event_channel`
+/// was full. Clones share the same counter.
+#[derive(Debug, Clone, Default)]
+pub struct DropCounter(Arc<AtomicU64>);
+
+impl DropCounter {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Forward `stream` to a channel of the given capacity, dropping the items
+/// that do not fit
+pub(crate) fn bounded<S, T>(
+    stream: S,
+    capacity: usize,
+) -> (impl Future<Output = ()>, Receiver<T>, DropCounter)
+where
+    S: Stream<Item = T>,
+{
+    let (mut tx, rx) = channel(capacity);
+    let dropped = DropCounter::default();
+    let counter = dropped.clone();
+    let forward = async move {
+        futures::pin_mut!(stream);
+        while let Some(item) = stream.next().await {
+            if let Err(e) = tx.try_send(item) {
+                if e.is_disconnected() {
+                    return;
+                }
+                counter.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    };
+    (forward, rx, dropped)
+}
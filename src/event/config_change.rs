@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    event::{path::number, AuditEvent, FieldValue},
+    packet::constants::AUDIT_CONFIG_CHANGE,
+};
+
+/// Operation reported by an `AUDIT_CONFIG_CHANGE` record
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConfigChangeOp {
+    /// `op=add_rule`
+    AddRule,
+    /// `op=remove_rule`
+    RemoveRule,
+    /// `op=updated_rules`: a watched file was moved or deleted, and the
+    /// watch rules were updated
+    UpdatedRules,
+    /// `op=trim`, see `Handle::trim`
+    Trim,
+    /// `op=make_equiv`, see `Handle::make_equiv`
+    MakeEquiv,
+    /// `op=set`: a status setting, such as `audit_enabled`, was changed
+    Set,
+    /// Any other operation
+    Other(String),
+}
+
+impl From<&str> for ConfigChangeOp {
+    fn from(op: &str) -> Self {
+        match op {
+            "add_rule" => ConfigChangeOp::AddRule,
+            "remove_rule" => ConfigChangeOp::RemoveRule,
+            "updated_rules" => ConfigChangeOp::UpdatedRules,
+            "trim" => ConfigChangeOp::Trim,
+            "make_equiv" => ConfigChangeOp::MakeEquiv,
+            "set" => ConfigChangeOp::Set,
+            op => ConfigChangeOp::Other(op.to_string()),
+        }
+    }
+}
+
+/// A change of the audit configuration, from an `AUDIT_CONFIG_CHANGE`
+/// record.
+///
+/// Status changes, such as `op=set audit_enabled=1 old=0`, name the setting
+/// in a field of its own: those fields are only available through the
+/// record.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConfigChangeRecord {
+    pub op: Option<ConfigChangeOp>,
+    /// Login uid of the process that made the change. `u32::MAX` means
+    /// unset.
+    pub auid: Option<u32>,
+    /// Session of the process that made the change. `u32::MAX` means unset.
+    pub ses: Option<u32>,
+    /// Key of the rule that was changed, if any
+    pub key: Option<String>,
+    /// Filter list of the rule that was changed, as in `RuleFlags`
+    pub list: Option<u32>,
+    /// Whether the change succeeded
+    pub res: Option<bool>,
+}
+
+impl ConfigChangeRecord {
+    /// Return `None` if the record is not an `AUDIT_CONFIG_CHANGE` record
+    pub fn from_record(record: &AuditEvent) -> Option<Self> {
+        if record.record_type != AUDIT_CONFIG_CHANGE {
+            return None;
+        }
+        Some(ConfigChangeRecord {
+            op: record
+                .field("op")
+                .and_then(FieldValue::as_str)
+                .map(ConfigChangeOp::from),
+            auid: number(record.field("auid")),
+            ses: number(record.field("ses")),
+            key: record
+                .field("key")
+                .filter(|key| key.as_str() != Some("(null)"))
+                .and_then(FieldValue::as_bytes)
+                .map(|key| String::from_utf8_lossy(key).into()),
+            list: number(record.field("list")),
+            res: record.field("res").and_then(FieldValue::as_bool),
+        })
+    }
+}
@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: MIT
+
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::PathBuf};
+
+use crate::{event::AuditEvent, packet::constants::AUDIT_CWD};
+
+/// Working directory of the process that triggered the event, from an
+/// `AUDIT_CWD` record. Relative names of [`PathRecord`](crate::PathRecord)s
+/// are relative to this directory.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CwdRecord {
+    /// The directory, with the bytes the kernel reported
+    pub cwd: PathBuf,
+}
+
+impl CwdRecord {
+    /// Decode the `cwd` field, which is hex-encoded if the path contains
+    /// special characters. Return `None` if the record is not a valid
+    /// `AUDIT_CWD` record.
+    pub fn from_record(record: &AuditEvent) -> Option<Self> {
+        if record.record_type != AUDIT_CWD {
+            return None;
+        }
+        let cwd = record.field("cwd")?.as_bytes()?;
+        Some(CwdRecord {
+            cwd: PathBuf::from(OsStr::from_bytes(cwd)),
+        })
+    }
+}
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MIT
+
+use std::convert::TryFrom;
+
+use crate::{event::AuditEvent, packet::constants::AUDIT_EXECVE};
+
+/// Command line of a process, from the `AUDIT_EXECVE` records of an event
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExecveRecord {
+    pub argc: usize,
+    /// The arguments, `argv[0]` included. Invalid UTF-8 sequences are
+    /// replaced with `U+FFFD`. There are fewer than `argc` arguments if
+    /// records were lost.
+    pub args: Vec<String>,
+}
+
+impl ExecveRecord {
+    /// Build the command line from the `AUDIT_EXECVE` records of an event.
+    ///
+    /// The kernel splits long command lines into several records, and long
+    /// arguments into several `a<N>[<M>]` fields: they are concatenated in
+    /// order, up to the first argument that is missing. Return `None` if
+    /// there is no `AUDIT_EXECVE` record with an `argc` field.
+    pub fn from_records<'a, I>(records: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a AuditEvent>,
+    {
+        let records: Vec<&AuditEvent> = records
+            .into_iter()
+            .filter(|record| record.record_type == AUDIT_EXECVE)
+            .collect();
+        let field =
+            |name: &str| records.iter().find_map(|record| record.field(name));
+
+        let argc = usize::try_from(field("argc")?.as_u64()?).ok()?;
+        // argc comes from the record: only go as far as the arguments that
+        // are actually present
+        let mut args = Vec::new();
+        for i in 0..argc {
+            let mut arg = Vec::new();
+            if let Some(value) = field(&format!("a{i}")) {
+                arg.extend_from_slice(value.as_bytes().unwrap_or_default());
+            } else {
+                for part in 0.. {
+                    match field(&format!("a{i}[{part}]")) {
+                        Some(value) => arg.extend_from_slice(
+                            value.as_bytes().unwrap_or_default(),
+                        ),
+                        None if part == 0 => {
+                            return Some(ExecveRecord { argc, args })
+                        }
+                        None => break,
+                    }
+                }
+            }
+            args.push(String::from_utf8_lossy(&arg).into_owned());
+        }
+        Some(ExecveRecord { argc, args })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::AuditMessage;
+
+    fn record(body: &str) -> AuditEvent {
+        let text = format!("audit(1699999999.123:42): {body}");
+        AuditEvent::try_from(&AuditMessage::Event((AUDIT_EXECVE, text)))
+            .unwrap()
+    }
+
+    #[test]
+    fn split_arguments() {
+        let records = [
+            record(r#"argc=3 a0="ls" a1_len=6 a1[0]="-l" a1[1]="a""#),
+            record(r#"a1[2]="h" a2=2F746D70"#),
+        ];
+        let execve = ExecveRecord::from_records(&records).unwrap();
+        assert_eq!(execve.argc, 3);
+        assert_eq!(execve.args, ["ls", "-lah", "/tmp"]);
+    }
+
+    #[test]
+    fn huge_argc() {
+        let records = [record(r#"argc=18446744073709551615 a0="ls""#)];
+        // argc does not fit in usize on 32-bit targets
+        if let Some(execve) = ExecveRecord::from_records(&records) {
+            assert_eq!(execve.args, ["ls"]);
+        }
+
+        let records = [record("argc=4000000000")];
+        assert!(ExecveRecord::from_records(&records)
+            .unwrap()
+            .args
+            .is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: MIT
+
+use std::convert::TryFrom;
+
+use crate::packet::constants::AUDIT_EXECVE;
+
+/// Value of a field of an audit record.
+///
+/// The variant is picked from the name of the field: for well-known fields
+/// such as `pid`, `exit` or `success`, unquoted values are parsed into
+/// numbers or booleans. Other values, or values that fail to parse, are
+/// kept as strings.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FieldValue {
+    /// Unsigned number, such as a pid or an uid
+    Uint(u64),
+    /// Signed number, such as the `exit` code of a syscall
+    Int(i64),
+    /// Plain value. Quotes around quoted values are removed.
+    Str(String),
+    /// Value the kernel hex-encoded because it contains characters such as
+    /// spaces or quotes. This holds the decoded bytes.
+    Hex(Vec<u8>),
+    /// Boolean, such as `success=yes` or `res=failed`
+    Bool(bool),
+}
+
+impl FieldValue {
+    /// Return the value as a string, if it is a plain value or hex-encoded
+    /// UTF-8 data.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            FieldValue::Str(s) => Some(s),
+            FieldValue::Hex(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+
+    /// Return the raw bytes of a plain or hex-encoded value
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            FieldValue::Str(s) => Some(s.as_bytes()),
+            FieldValue::Hex(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Return the value as an unsigned number. Plain values are parsed as
+    /// decimal numbers.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            FieldValue::Uint(value) => Some(*value),
+            FieldValue::Int(value) => u64::try_from(*value).ok(),
+            FieldValue::Str(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Return the value as a signed number. Plain values are parsed as
+    /// decimal numbers.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            FieldValue::Int(value) => Some(*value),
+            FieldValue::Uint(value) => i64::try_from(*value).ok(),
+            FieldValue::Str(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            FieldValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Fields holding unsigned decimal numbers
+const UINT_FIELDS: &[&str] = &[
+    "argc", "auid", "egid", "euid", "fsgid", "fsuid", "gid", "inode", "item",
+    "items", "ogid", "old-auid", "old-ses", "ouid", "pid", "ppid", "ses",
+    "sgid", "suid", "syscall", "uid",
+];
+
+/// Fields holding signed decimal numbers
+const INT_FIELDS: &[&str] = &["exit"];
+
+/// Fields whose value is a string that may come from an untrusted source.
+/// The kernel either quotes them, or hex-encodes them if they contain
+/// special characters.
+const UNTRUSTED_STRING_FIELDS: &[&str] = &[
+    "acct",
+    "cmd",
+    "comm",
+    "cwd",
+    "data",
+    "dir",
+    "exe",
+    "file",
+    "key",
+    "name",
+    "new",
+    "old",
+    "path",
+    "proctitle",
+    "root_dir",
+    "watch",
+];
+
+pub(crate) fn parse_unquoted(
+    record_type: u16,
+    key: &str,
+    value: &str,
+) -> FieldValue {
+    if is_untrusted_string_field(key)
+        || (record_type == AUDIT_EXECVE && is_execve_argument(key))
+    {
+        if let Some(bytes) = decode_hex(value) {
+            return FieldValue::Hex(bytes);
+        }
+    }
+    parse_typed(key, value)
+        .unwrap_or_else(|| FieldValue::Str(value.to_string()))
+}
+
+fn parse_typed(key: &str, value: &str) -> Option<FieldValue> {
+    if UINT_FIELDS.contains(&key) {
+        value.parse().ok().map(FieldValue::Uint)
+    } else if INT_FIELDS.contains(&key) {
+        value.parse().ok().map(FieldValue::Int)
+    } else if key == "success" || key == "res" {
+        match value {
+            "yes" | "success" | "1" => Some(FieldValue::Bool(true)),
+            "no" | "failed" | "0" => Some(FieldValue::Bool(false)),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+fn is_untrusted_string_field(key: &str) -> bool {
+    UNTRUSTED_STRING_FIELDS.contains(&key)
+}
+
+/// Return `true` for the `a<N>` and `a<N>[<M>]` arguments of `AUDIT_EXECVE`
+/// records. In other records, such as `AUDIT_SYSCALL`, `a<N>` are plain
+/// hexadecimal numbers.
+fn is_execve_argument(key: &str) -> bool {
+    let index = match key.strip_prefix('a') {
+        Some(index) => index,
+        None => return false,
+    };
+    let (index, part) = match index.split_once('[') {
+        Some((index, part)) => (index, part.strip_suffix(']')),
+        None => (index, Some("0")),
+    };
+    let is_number =
+        |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    is_number(index) && part.is_some_and(is_number)
+}
+
+/// Decode an hex string such as `2F746D70`. Return `None` if the string is
+/// not valid hexadecimal.
+pub(crate) fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.is_empty() || value.len() % 2 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: MIT
+
+use futures::{
+    future,
+    stream::{Stream, StreamExt},
+};
+use netlink_packet_core::NetlinkMessage;
+
+use crate::packet::AuditMessage;
+
+/// Only keep the messages whose netlink message type is one of `types`, for
+/// instance `&[AUDIT_SYSCALL, AUDIT_EXECVE]`.
+///
+/// The filter only looks at the netlink header, so discarded records are
+/// never parsed.
+///
+/// Beware that filtering out `AUDIT_EOE` before an [`EventReassembler`]
+/// delays events until its idle timeout.
+///
+/// [`EventReassembler`]: crate::EventReassembler
+pub fn filter_record_types<S>(
+    stream: S,
+    types: &[u16],
+) -> impl Stream<Item = NetlinkMessage<AuditMessage>>
+where
+    S: Stream<Item = NetlinkMessage<AuditMessage>>,
+{
+    let types = types.to_vec();
+    stream.filter(move |message| {
+        future::ready(types.contains(&message.header.message_type))
+    })
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MIT
+
+use std::fmt::Write;
+
+use crate::{event::AuditEvent, record_type_name, FieldValue};
+
+impl AuditEvent {
+    /// Render the record as a single line JSON object, for instance
+    /// `{"type":"CWD","record_type":1307,"timestamp":1699999999.123,
+    /// "serial":4567,"fields":{"cwd":"/root"}}`. Joined with newlines,
+    /// this gives the newline-delimited JSON most log collectors ingest.
+    ///
+    /// `type` is the record type name, or `UNKNOWN[<type>]` like auditd
+    /// prints it. The fields are sorted by name, and keep the type of their
+    /// [`FieldValue`]: numbers and booleans are not quoted, and hex-encoded
+    /// values are decoded. Decoded values that are not valid UTF-8 are kept
+    /// hex-encoded, as the kernel sent them.
+    ///
+    /// ```
+    /// use audit::parse_event;
+    ///
+    /// let event =
+    ///     parse_event(b"audit(1699999999.123:4567): pid=42 comm=\"ls\"")
+    ///         .unwrap();
+    /// assert_eq!(
+    ///     event.to_json(),
+    ///     "{\"type\":\"UNKNOWN[0]\",\"record_type\":0,\
+    ///      \"timestamp\":1699999999.123,\"serial\":4567,\
+    ///      \"fields\":{\"comm\":\"ls\",\"pid\":42}}",
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\"type\":");
+        match record_type_name(self.record_type) {
+            Some(name) => push_str(&mut json, name),
+            None => {
+                push_str(&mut json, &format!("UNKNOWN[{}]", self.record_type))
+            }
+        }
+        let _ = write!(
+            json,
+            ",\"record_type\":{},\"timestamp\":{}.{:03},\"serial\":{}",
+            self.record_type,
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_millis(),
+            self.serial,
+        );
+
+        json.push_str(",\"fields\":{");
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_by_key(|(name, _)| *name);
+        for (i, (name, value)) in fields.into_iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            push_str(&mut json, name);
+            json.push(':');
+            push_value(&mut json, value);
+        }
+        json.push_str("}}");
+        json
+    }
+}
+
+fn push_value(json: &mut String, value: &FieldValue) {
+    match value {
+        FieldValue::Uint(value) => {
+            let _ = write!(json, "{value}");
+        }
+        FieldValue::Int(value) => {
+            let _ = write!(json, "{value}");
+        }
+        FieldValue::Bool(value) => {
+            let _ = write!(json, "{value}");
+        }
+        FieldValue::Str(value) => push_str(json, value),
+        FieldValue::Hex(bytes) => match std::str::from_utf8(bytes) {
+            Ok(value) => push_str(json, value),
+            Err(_) => {
+                let hex: String =
+                    bytes.iter().map(|b| format!("{b:02X}")).collect();
+                push_str(json, &hex);
+            }
+        },
+    }
+}
+
+/// Append `s` as a quoted JSON string
+fn push_str(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
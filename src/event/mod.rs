@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MIT
+
+mod args;
+
+mod avc;
+pub use self::avc::*;
+
+mod channel;
+pub(crate) use self::channel::bounded;
+pub use self::channel::DropCounter;
+
+mod config_change;
+pub use self::config_change::*;
+
+mod cwd;
+pub use self::cwd::*;
+
+mod execve;
+pub use self::execve::*;
+
+mod field;
+pub use self::field::*;
+
+mod filter;
+pub use self::filter::*;
+
+mod json;
+
+mod parser;
+pub use self::parser::*;
+
+mod path;
+pub use self::path::*;
+
+mod proctitle;
+pub use self::proctitle::*;
+
+mod reassembler;
+pub use self::reassembler::*;
+
+mod syscall;
+pub use self::syscall::*;
+
+#[cfg(feature = "resolve")]
+mod resolve;
+#[cfg(feature = "resolve")]
+pub use self::resolve::*;
@@ -0,0 +1,460 @@
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use thiserror::Error;
+
+use crate::{
+    event::{parse_unquoted, FieldValue},
+    packet::{
+        constants::{AUDIT_AVC, AUDIT_EXECVE, AUDIT_USER_AVC},
+        AuditMessage,
+    },
+};
+
+#[derive(Clone, Eq, PartialEq, Debug, Error)]
+pub enum ParseError {
+    #[error("Record is not valid UTF-8")]
+    InvalidUtf8,
+
+    #[error("Missing audit(timestamp:serial) header in {0:?}")]
+    MissingHeader(String),
+
+    #[error("Invalid timestamp {0:?}")]
+    InvalidTimestamp(String),
+
+    #[error("Invalid serial number {0:?}")]
+    InvalidSerial(String),
+
+    #[error("Unterminated quoted value for field {0:?}")]
+    UnterminatedQuote(String),
+
+    #[error("Message of type {0} is not an audit record")]
+    NotARecord(u16),
+}
+
+/// An audit record, such as
+/// `audit(1234.567:89): syscall=59 success=yes exit=0`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AuditEvent {
+    /// Netlink message type of the record, such as `AUDIT_SYSCALL`, or 0 if
+    /// it was not known when parsing.
+    pub record_type: u16,
+    /// Time of the event, since the UNIX epoch
+    pub timestamp: Duration,
+    /// Serial number of the event. All the records of an event share the
+    /// same timestamp and serial number.
+    pub serial: u64,
+    pub fields: HashMap<String, FieldValue>,
+}
+
+impl AuditEvent {
+    /// Get the value of the given field
+    pub fn field(&self, name: &str) -> Option<&FieldValue> {
+        self.fields.get(name)
+    }
+
+    /// Get the identifier of the event this record belongs to. Parsed
+    /// records always have a timestamp that [`SystemTime`] can hold; this
+    /// panics if `timestamp` was set to one it cannot.
+    pub fn id(&self) -> EventId {
+        EventId {
+            timestamp: UNIX_EPOCH + self.timestamp,
+            serial: self.serial,
+        }
+    }
+}
+
+/// Identifier of an audit event, `audit(<timestamp>:<serial>)`, shared by
+/// all its records
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct EventId {
+    pub timestamp: SystemTime,
+    pub serial: u64,
+}
+
+impl EventId {
+    /// Parse the identifier at the beginning of a record, such as
+    /// `audit(1699999999.123:4567):` or `msg=audit(1699999999.123:4567):`.
+    /// The rest of the record is ignored.
+    pub fn parse(prefix: &[u8]) -> Result<Self, ParseError> {
+        // Only the identifier has to be valid UTF-8
+        let text = match std::str::from_utf8(prefix) {
+            Ok(text) => text,
+            Err(e) => std::str::from_utf8(&prefix[..e.valid_up_to()])
+                .map_err(|_| ParseError::InvalidUtf8)?,
+        };
+        let (timestamp, serial, _) = parse_header(text)?;
+        Ok(EventId {
+            timestamp: UNIX_EPOCH + timestamp,
+            serial,
+        })
+    }
+}
+
+impl TryFrom<&AuditMessage> for AuditEvent {
+    type Error = ParseError;
+
+    fn try_from(message: &AuditMessage) -> Result<Self, Self::Error> {
+        match message {
+            AuditMessage::Event((record_type, data))
+            | AuditMessage::Other((record_type, data)) => {
+                parse_record(data.as_bytes(), *record_type)
+            }
+            message => Err(ParseError::NotARecord(message.message_type())),
+        }
+    }
+}
+
+/// Parse the text of an audit record. The text can either be the payload
+/// of a netlink message (`audit(1234.567:89): ...`), or a line of the audit
+/// log (`type=SYSCALL msg=audit(1234.567:89): ...`).
+///
+/// The `record_type` of the returned event is always 0, since the type of
+/// the record is carried by the netlink message header.
+pub fn parse_event(bytes: &[u8]) -> Result<AuditEvent, ParseError> {
+    let mut event = parse_record(bytes, 0)?;
+    event.record_type = 0;
+    Ok(event)
+}
+
+/// Parse the text of a record of the given type. The type is needed to
+/// know how to decode some fields, such as the arguments of `AUDIT_EXECVE`
+/// records. If it is 0, it is guessed from the `type=` prefix of log lines
+/// when it matters.
+fn parse_record(
+    bytes: &[u8],
+    record_type: u16,
+) -> Result<AuditEvent, ParseError> {
+    let text =
+        std::str::from_utf8(bytes).map_err(|_| ParseError::InvalidUtf8)?;
+    let (timestamp, serial, body) = parse_header(text)?;
+    let record_type = match record_type {
+        0 if text.starts_with("type=EXECVE ") => AUDIT_EXECVE,
+        0 if text.starts_with("type=AVC ") => AUDIT_AVC,
+        0 if text.starts_with("type=USER_AVC ") => AUDIT_USER_AVC,
+        record_type => record_type,
+    };
+    let mut fields = HashMap::new();
+    parse_fields(body, record_type, &mut fields)?;
+    if record_type == AUDIT_AVC || record_type == AUDIT_USER_AVC {
+        parse_avc_decision(body, &mut fields);
+    }
+    Ok(AuditEvent {
+        record_type,
+        timestamp,
+        serial,
+        fields,
+    })
+}
+
+/// Parse the `audit(<secs>.<millis>:<serial>):` header, and return the
+/// timestamp, the serial number and the rest of the record.
+fn parse_header(text: &str) -> Result<(Duration, u64, &str), ParseError> {
+    let missing_header = || ParseError::MissingHeader(text.to_string());
+
+    let start = text.find("audit(").ok_or_else(missing_header)?;
+    let header = &text[start + "audit(".len()..];
+    let end = header.find("):").ok_or_else(missing_header)?;
+    let (id, body) = (&header[..end], &header[end + 2..]);
+    let (ts, serial) = id.split_once(':').ok_or_else(missing_header)?;
+
+    let invalid_timestamp = || ParseError::InvalidTimestamp(ts.to_string());
+    let (secs, fraction) = ts.split_once('.').unwrap_or((ts, "0"));
+    let secs = secs.parse::<u64>().map_err(|_| invalid_timestamp())?;
+    // The kernel prints milliseconds, but accept any sub-second precision
+    if fraction.is_empty()
+        || fraction.len() > 9
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid_timestamp());
+    }
+    let nanos = fraction.parse::<u32>().map_err(|_| invalid_timestamp())?
+        * 10u32.pow(9 - fraction.len() as u32);
+    let serial = serial
+        .parse::<u64>()
+        .map_err(|_| ParseError::InvalidSerial(serial.to_string()))?;
+
+    let timestamp = Duration::new(secs, nanos);
+    // Reject timestamps that cannot be turned into a SystemTime, so that
+    // `id()` cannot overflow
+    if UNIX_EPOCH.checked_add(timestamp).is_none() {
+        return Err(invalid_timestamp());
+    }
+
+    Ok((timestamp, serial, body))
+}
+
+/// Parse the `avc:  denied  { read write } for` prefix of SELinux records,
+/// which is not made of fields. Like libauparse, store the decision in
+/// `seresult` and the permissions in `seperms`, separated by commas.
+fn parse_avc_decision(body: &str, fields: &mut HashMap<String, FieldValue>) {
+    let parse = || {
+        // User space AVCs have the prefix in msg='...'
+        let rest = &body[body.find("avc:")? + "avc:".len()..];
+        let rest = rest.trim_start_matches(is_separator);
+        let (decision, rest) = rest.split_once(is_separator)?;
+        let rest = rest.trim_start_matches(is_separator).strip_prefix('{')?;
+        let perms = &rest[..rest.find('}')?];
+        let perms: Vec<&str> = perms.split_whitespace().collect();
+        Some((decision, perms.join(",")))
+    };
+    if let Some((decision, perms)) = parse() {
+        fields
+            .entry("seresult".to_string())
+            .or_insert_with(|| FieldValue::Str(decision.to_string()));
+        fields
+            .entry("seperms".to_string())
+            .or_insert(FieldValue::Str(perms));
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    // auditd separates the enriched fields with a group separator (0x1d)
+    c.is_ascii_whitespace() || c == '\x1d'
+}
+
+fn parse_fields(
+    text: &str,
+    record_type: u16,
+    fields: &mut HashMap<String, FieldValue>,
+) -> Result<(), ParseError> {
+    for field in RawFields::new(text) {
+        let (key, value, quoted) = field?;
+        let value = if quoted {
+            FieldValue::Str(value.to_string())
+        } else {
+            parse_unquoted(record_type, key, value)
+        };
+        fields.insert(key.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Tokenizer shared by the owned and borrowed parsers. It yields the key,
+/// the raw value and whether the value was quoted.
+#[derive(Debug, Clone)]
+struct RawFields<'a> {
+    text: &'a str,
+    /// Rest of the record, while the fields embedded in `msg='...'` are
+    /// being read
+    outer: Option<&'a str>,
+}
+
+impl<'a> RawFields<'a> {
+    fn new(text: &'a str) -> Self {
+        RawFields { text, outer: None }
+    }
+}
+
+impl<'a> Iterator for RawFields<'a> {
+    type Item = Result<(&'a str, &'a str, bool), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.text = self.text.trim_start_matches(is_separator);
+            if self.text.is_empty() {
+                self.text = self.outer.take()?;
+                continue;
+            }
+            let text = self.text;
+
+            let token_end = text.find(is_separator).unwrap_or(text.len());
+            let key_end = match text[..token_end].find('=') {
+                Some(i) => i,
+                None => {
+                    // Some records contain bare words, like `avc: denied`.
+                    // They are not fields, skip them.
+                    self.text = &text[token_end..];
+                    continue;
+                }
+            };
+            let key = &text[..key_end];
+            let rest = &text[key_end + 1..];
+
+            match rest.chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => {
+                    let end = match rest[1..].find(quote) {
+                        Some(end) => end + 1,
+                        None => {
+                            self.text = "";
+                            self.outer = None;
+                            return Some(Err(ParseError::UnterminatedQuote(
+                                key.to_string(),
+                            )));
+                        }
+                    };
+                    let value = &rest[1..end];
+                    self.text = &rest[end + 1..];
+                    if quote == '\'' && key == "msg" {
+                        // User space messages have their own fields embedded
+                        // in msg='...'. They cannot contain another msg='...',
+                        // since the first quote ends the value.
+                        self.outer = Some(self.text);
+                        self.text = value;
+                        continue;
+                    }
+                    return Some(Ok((key, value, true)));
+                }
+                _ => {
+                    // The value goes up to the next separator, `=` included.
+                    let end = rest.find(is_separator).unwrap_or(rest.len());
+                    self.text = &rest[end..];
+                    return Some(Ok((key, &rest[..end], false)));
+                }
+            }
+        }
+    }
+}
+
+/// An audit record borrowed from the buffer it was received in.
+///
+/// Unlike [`AuditEvent`], parsing it only reads the header: the fields are
+/// tokenized on demand by [`AuditEventRef::fields`], without allocating.
+/// This suits hot loops that only look at a few fields of each record.
+///
+/// ```
+/// use audit::AuditEventRef;
+///
+/// let record = b"audit(1699999999.123:4567): syscall=59 comm=\"ls\" key=\"exec\"";
+/// let event = AuditEventRef::parse(record).unwrap();
+/// assert_eq!(event.serial, 4567);
+/// assert_eq!(event.field("key"), Some("exec"));
+/// assert_eq!(event.fields().next(), Some(("syscall", "59")));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct AuditEventRef<'a> {
+    /// Netlink message type of the record, or 0 if it was not known when
+    /// parsing
+    pub record_type: u16,
+    /// Time of the event, since the UNIX epoch
+    pub timestamp: Duration,
+    /// Serial number of the event
+    pub serial: u64,
+    text: &'a str,
+    body: &'a str,
+}
+
+impl<'a> AuditEventRef<'a> {
+    /// Parse the header of a record, like [`parse_event`] does. The fields
+    /// are not checked until they are read.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ParseError> {
+        Self::parse_record(bytes, 0)
+    }
+
+    fn parse_record(
+        bytes: &'a [u8],
+        record_type: u16,
+    ) -> Result<Self, ParseError> {
+        let text =
+            std::str::from_utf8(bytes).map_err(|_| ParseError::InvalidUtf8)?;
+        let (timestamp, serial, body) = parse_header(text)?;
+        Ok(AuditEventRef {
+            record_type,
+            timestamp,
+            serial,
+            text,
+            body,
+        })
+    }
+
+    /// Iterate over the `(key, value)` pairs of the record, in order. The
+    /// fields embedded in the `msg='...'` of user space messages are
+    /// included, as with [`AuditEvent`].
+    ///
+    /// Values are returned as they appear in the record, without their
+    /// quotes: unlike [`FieldValue`], hex encoded strings are not decoded.
+    /// The iteration stops at a quoted value that is not terminated, use
+    /// [`AuditEventRef::to_event`] to get the error.
+    pub fn fields(&self) -> RecordFields<'a> {
+        RecordFields(RawFields::new(self.body))
+    }
+
+    /// Get the raw value of the given field. If it appears several times,
+    /// the last value is returned, like [`AuditEvent::field`] does.
+    pub fn field(&self, name: &str) -> Option<&'a str> {
+        self.fields()
+            .filter(|(key, _)| *key == name)
+            .last()
+            .map(|(_, value)| value)
+    }
+
+    /// Get the identifier of the event this record belongs to. Parsed
+    /// records always have a timestamp that [`SystemTime`] can hold; this
+    /// panics if `timestamp` was set to one it cannot.
+    pub fn id(&self) -> EventId {
+        EventId {
+            timestamp: UNIX_EPOCH + self.timestamp,
+            serial: self.serial,
+        }
+    }
+
+    /// Parse all the fields, into an owned [`AuditEvent`]
+    pub fn to_event(&self) -> Result<AuditEvent, ParseError> {
+        let mut event = parse_record(self.text.as_bytes(), self.record_type)?;
+        event.record_type = self.record_type;
+        Ok(event)
+    }
+}
+
+impl<'a> TryFrom<&'a AuditMessage> for AuditEventRef<'a> {
+    type Error = ParseError;
+
+    fn try_from(message: &'a AuditMessage) -> Result<Self, Self::Error> {
+        match message {
+            AuditMessage::Event((record_type, data))
+            | AuditMessage::Other((record_type, data)) => {
+                Self::parse_record(data.as_bytes(), *record_type)
+            }
+            message => Err(ParseError::NotARecord(message.message_type())),
+        }
+    }
+}
+
+/// Iterator over the fields of an [`AuditEventRef`]
+#[derive(Debug, Clone)]
+pub struct RecordFields<'a>(RawFields<'a>);
+
+impl<'a> Iterator for RecordFields<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.0.next()? {
+            Ok((key, value, _)) => Some((key, value)),
+            Err(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_id() {
+        let id =
+            EventId::parse(b"audit(1699999999.123:4567): syscall=59").unwrap();
+        assert_eq!(id.serial, 4567);
+        assert_eq!(
+            id.timestamp,
+            UNIX_EPOCH + Duration::from_millis(1_699_999_999_123)
+        );
+    }
+
+    #[test]
+    fn reject_out_of_range_timestamp() {
+        let record = "audit(18446744073709551615.999:1): syscall=59";
+        assert_eq!(
+            EventId::parse(record.as_bytes()),
+            Err(ParseError::InvalidTimestamp(
+                "18446744073709551615.999".to_string()
+            ))
+        );
+        assert!(parse_event(record.as_bytes()).is_err());
+        assert!(AuditEventRef::parse(record.as_bytes()).is_err());
+    }
+}
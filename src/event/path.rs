@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MIT
+
+use std::convert::TryFrom;
+
+use crate::{
+    event::{AuditEvent, FieldValue},
+    packet::constants::AUDIT_PATH,
+};
+
+/// A file accessed by a syscall, from an `AUDIT_PATH` record.
+///
+/// Only `item` is always present: the kernel omits the other fields when
+/// they are unknown, for instance when the syscall failed to find the file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PathRecord {
+    /// Index of the record among the `AUDIT_PATH` records of the event
+    pub item: u32,
+    /// Path as given to the syscall. Invalid UTF-8 sequences are replaced
+    /// with `U+FFFD`.
+    pub name: Option<String>,
+    pub inode: Option<u64>,
+    /// Major and minor numbers of the device holding the file
+    pub dev: Option<(u32, u32)>,
+    /// File type and permissions, as in `st_mode`
+    pub mode: Option<u32>,
+    pub ouid: Option<u32>,
+    pub ogid: Option<u32>,
+    /// Major and minor numbers of the device, for device files
+    pub rdev: Option<(u32, u32)>,
+    /// What the syscall did with the path, such as `NORMAL`, `PARENT`,
+    /// `CREATE` or `DELETE`
+    pub nametype: Option<String>,
+}
+
+impl PathRecord {
+    /// Return `None` if the record is not a valid `AUDIT_PATH` record
+    pub fn from_record(record: &AuditEvent) -> Option<Self> {
+        if record.record_type != AUDIT_PATH {
+            return None;
+        }
+        let string = |name: &str| record.field(name)?.as_str();
+        let device = |name: &str| {
+            let (major, minor) = string(name)?.split_once(':')?;
+            Some((
+                u32::from_str_radix(major, 16).ok()?,
+                u32::from_str_radix(minor, 16).ok()?,
+            ))
+        };
+
+        Some(PathRecord {
+            item: number(record.field("item"))?,
+            name: record
+                .field("name")
+                .filter(|name| name.as_str() != Some("(null)"))
+                .and_then(FieldValue::as_bytes)
+                .map(|name| String::from_utf8_lossy(name).into()),
+            inode: number(record.field("inode")),
+            dev: device("dev"),
+            mode: string("mode")
+                .and_then(|mode| u32::from_str_radix(mode, 8).ok()),
+            ouid: number(record.field("ouid")),
+            ogid: number(record.field("ogid")),
+            rdev: device("rdev"),
+            nametype: string("nametype").map(String::from),
+        })
+    }
+}
+
+pub(crate) fn number<T: TryFrom<u64>>(value: Option<&FieldValue>) -> Option<T> {
+    T::try_from(value?.as_u64()?).ok()
+}
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: MIT
+
+use crate::{event::AuditEvent, packet::constants::AUDIT_PROCTITLE};
+
+/// Command line of the process that triggered the event, from an
+/// `AUDIT_PROCTITLE` record.
+///
+/// Unlike [`ExecveRecord`](crate::ExecveRecord), this is emitted for every
+/// syscall event, but the kernel truncates it to 128 bytes so the last
+/// argument may be cut.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ProctitleRecord {
+    /// The arguments, `argv[0]` included. Invalid UTF-8 sequences are
+    /// replaced with `U+FFFD`.
+    pub cmdline: Vec<String>,
+}
+
+impl ProctitleRecord {
+    /// Decode the `proctitle` field, whose arguments are separated by NUL
+    /// bytes. Return `None` if the record is not a valid `AUDIT_PROCTITLE`
+    /// record.
+    pub fn from_record(record: &AuditEvent) -> Option<Self> {
+        if record.record_type != AUDIT_PROCTITLE {
+            return None;
+        }
+        let mut bytes = record.field("proctitle")?.as_bytes()?;
+        while let Some(rest) = bytes.strip_suffix(&[0]) {
+            bytes = rest;
+        }
+        let cmdline = bytes
+            .split(|b| *b == 0)
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect();
+        Some(ProctitleRecord { cmdline })
+    }
+}
@@ -0,0 +1,300 @@
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    convert::TryFrom,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::stream::Stream;
+use netlink_packet_core::{NetlinkMessage, NetlinkPayload};
+
+use crate::{
+    event::{
+        AuditEvent, AvcRecord, CwdRecord, ExecveRecord, ParseError, PathRecord,
+        ProctitleRecord, SyscallRecord,
+    },
+    packet::{
+        constants::{AUDIT_EOE, AUDIT_FIRST_USER_MSG},
+        AuditMessage,
+    },
+    time::{sleep, Sleep},
+};
+
+/// How long [`EventReassembler`] waits for more records of an event by
+/// default.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A complete audit event, made of all the records sharing the same
+/// `audit(timestamp:serial)` identifier.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Event {
+    pub serial: u64,
+    /// Time of the event, since the UNIX epoch
+    pub timestamp: Duration,
+    /// The records of the event, in the order they were received. The
+    /// `AUDIT_EOE` record is not included.
+    pub records: Vec<AuditEvent>,
+}
+
+impl Event {
+    /// Return the first record of the given type
+    pub fn record(&self, record_type: u16) -> Option<&AuditEvent> {
+        self.records.iter().find(|r| r.record_type == record_type)
+    }
+
+    /// Return the syscall that triggered the event, from its `AUDIT_SYSCALL`
+    /// record
+    pub fn syscall(&self) -> Option<SyscallRecord> {
+        self.records.iter().find_map(SyscallRecord::from_record)
+    }
+
+    /// Return the command line of the process, if this is an `execve` event
+    pub fn execve(&self) -> Option<ExecveRecord> {
+        ExecveRecord::from_records(&self.records)
+    }
+
+    /// Return the command line of the process that triggered the event, from
+    /// its `AUDIT_PROCTITLE` record
+    pub fn proctitle(&self) -> Option<ProctitleRecord> {
+        self.records.iter().find_map(ProctitleRecord::from_record)
+    }
+
+    /// Return the working directory of the process, from the `AUDIT_CWD`
+    /// record of the event
+    pub fn cwd(&self) -> Option<CwdRecord> {
+        self.records.iter().find_map(CwdRecord::from_record)
+    }
+
+    /// Return the SELinux access decisions of the event, from its `AUDIT_AVC`
+    /// records. There is one record per object and class.
+    pub fn avcs(&self) -> Vec<AvcRecord> {
+        self.records
+            .iter()
+            .filter_map(AvcRecord::from_record)
+            .collect()
+    }
+
+    /// Return the files accessed by the syscall of this event, ordered by
+    /// `item`
+    pub fn paths(&self) -> Vec<PathRecord> {
+        let mut paths: Vec<PathRecord> = self
+            .records
+            .iter()
+            .filter_map(PathRecord::from_record)
+            .collect();
+        paths.sort_by_key(|path| path.item);
+        paths
+    }
+}
+
+struct PendingEvent {
+    event: Event,
+    last_update: Instant,
+}
+
+/// Stream adapter that groups the records received from the kernel into
+/// [`Event`]s.
+///
+/// An event is yielded once its `AUDIT_EOE` record is received. Since
+/// single-record events have no `AUDIT_EOE` record, and since records can
+/// be lost, events that did not receive any record for the idle timeout are
+/// yielded as they are. The timeout relies on the timer of the runtime
+/// selected by the `tokio_socket` or `smol_socket` feature. Without any of
+/// them, pending events are only checked for expiration when a new message
+/// arrives.
+///
+/// Messages that are not audit records are ignored.
+pub struct EventReassembler<S> {
+    stream: S,
+    idle_timeout: Duration,
+    pending: BTreeMap<u64, PendingEvent>,
+    ready: VecDeque<Event>,
+    timer: Option<(Instant, Sleep)>,
+    done: bool,
+}
+
+impl<S> EventReassembler<S>
+where
+    S: Stream<Item = NetlinkMessage<AuditMessage>> + Unpin,
+{
+    pub fn new(stream: S) -> Self {
+        Self::with_idle_timeout(stream, DEFAULT_IDLE_TIMEOUT)
+    }
+
+    pub fn with_idle_timeout(stream: S, idle_timeout: Duration) -> Self {
+        EventReassembler {
+            stream,
+            idle_timeout,
+            pending: BTreeMap::new(),
+            ready: VecDeque::new(),
+            timer: None,
+            done: false,
+        }
+    }
+
+    fn handle_message(
+        &mut self,
+        message: NetlinkMessage<AuditMessage>,
+    ) -> Result<(), ParseError> {
+        let message = match message.payload {
+            NetlinkPayload::InnerMessage(message)
+                if message.message_type() >= AUDIT_FIRST_USER_MSG =>
+            {
+                message
+            }
+            _ => return Ok(()),
+        };
+        let record = AuditEvent::try_from(&message)?;
+
+        if record.record_type == AUDIT_EOE {
+            if let Some(pending) = self.pending.remove(&record.serial) {
+                self.ready.push_back(pending.event);
+            }
+            return Ok(());
+        }
+
+        let pending =
+            self.pending
+                .entry(record.serial)
+                .or_insert_with(|| PendingEvent {
+                    event: Event {
+                        serial: record.serial,
+                        timestamp: record.timestamp,
+                        records: Vec::new(),
+                    },
+                    last_update: Instant::now(),
+                });
+        pending.last_update = Instant::now();
+        pending.event.records.push(record);
+        Ok(())
+    }
+
+    /// Move the events that expired to the ready queue, and return the
+    /// deadline of the next event to expire.
+    fn expire(&mut self, now: Instant) -> Option<Instant> {
+        let idle_timeout = self.idle_timeout;
+        let expired: Vec<u64> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| now.duration_since(p.last_update) >= idle_timeout)
+            .map(|(serial, _)| *serial)
+            .collect();
+        for serial in expired {
+            if let Some(pending) = self.pending.remove(&serial) {
+                self.ready.push_back(pending.event);
+            }
+        }
+        self.pending
+            .values()
+            .map(|p| p.last_update + idle_timeout)
+            .min()
+    }
+}
+
+impl<S> Stream for EventReassembler<S>
+where
+    S: Stream<Item = NetlinkMessage<AuditMessage>> + Unpin,
+{
+    type Item = Result<Event, ParseError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.ready.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            if this.done {
+                // Flush whatever is left
+                let pending = std::mem::take(&mut this.pending);
+                this.ready.extend(pending.into_values().map(|p| p.event));
+                if this.ready.is_empty() {
+                    return Poll::Ready(None);
+                }
+                continue;
+            }
+
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(message)) => {
+                    if let Err(e) = this.handle_message(message) {
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                    this.expire(Instant::now());
+                }
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => {
+                    let deadline = this.expire(Instant::now());
+                    if !this.ready.is_empty() {
+                        continue;
+                    }
+                    // Nothing is pending: wait for the inner stream, which
+                    // registered the waker
+                    let deadline = match deadline {
+                        Some(deadline) => deadline,
+                        None => {
+                            this.timer = None;
+                            return Poll::Pending;
+                        }
+                    };
+                    let timer = match this.timer {
+                        Some((d, ref mut timer)) if d == deadline => timer,
+                        _ => {
+                            let delay = deadline
+                                .saturating_duration_since(Instant::now());
+                            &mut this.timer.insert((deadline, sleep(delay))).1
+                        }
+                    };
+                    match timer.as_mut().poll(cx) {
+                        Poll::Ready(()) => this.timer = None,
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{
+        stream::{self, StreamExt},
+        task::noop_waker_ref,
+    };
+
+    use super::*;
+    use crate::packet::constants::AUDIT_SYSCALL;
+
+    fn record(record_type: u16, text: &str) -> NetlinkMessage<AuditMessage> {
+        NetlinkMessage::from(AuditMessage::Event((record_type, text.into())))
+    }
+
+    #[test]
+    fn idle_stream_is_pending() {
+        let mut events = EventReassembler::new(stream::pending());
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(events.poll_next_unpin(&mut cx).is_pending());
+        assert!(events.poll_next_unpin(&mut cx).is_pending());
+    }
+
+    #[test]
+    fn idle_stream_is_pending_after_complete_event() {
+        let records = stream::iter(vec![
+            record(AUDIT_SYSCALL, "audit(1699999999.123:42): syscall=59"),
+            record(AUDIT_EOE, "audit(1699999999.123:42): "),
+        ])
+        .chain(stream::pending());
+        let mut events = EventReassembler::new(records);
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match events.poll_next_unpin(&mut cx) {
+            Poll::Ready(Some(Ok(event))) => assert_eq!(event.serial, 42),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(events.poll_next_unpin(&mut cx).is_pending());
+    }
+}
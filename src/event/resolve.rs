@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::HashMap, convert::TryFrom, ffi::CStr, mem::MaybeUninit,
+    os::raw::c_char, sync::Mutex,
+};
+
+use crate::event::AuditEvent;
+
+/// Value of id fields such as `auid` when they are not set
+const UNSET_ID: u32 = u32::MAX;
+
+static USERS: Mutex<Option<HashMap<u32, Option<String>>>> = Mutex::new(None);
+static GROUPS: Mutex<Option<HashMap<u32, Option<String>>>> = Mutex::new(None);
+
+/// Return the name of the user with the given uid, using the system user
+/// database (`getpwuid_r`). Results are cached for the life of the process.
+pub fn resolve_uid(uid: u32) -> Option<String> {
+    cached(&USERS, uid, || lookup_user(uid))
+}
+
+/// Return the name of the group with the given gid, using the system group
+/// database (`getgrgid_r`). Results are cached for the life of the process.
+pub fn resolve_gid(gid: u32) -> Option<String> {
+    cached(&GROUPS, gid, || lookup_group(gid))
+}
+
+impl AuditEvent {
+    /// Return the name of the user whose uid is in the given field, for
+    /// instance `auid` or `euid`, like `ausearch -i` does
+    pub fn resolved_user(&self, field: &str) -> Option<String> {
+        resolve_uid(self.id_field(field)?)
+    }
+
+    /// Return the name of the group whose gid is in the given field, for
+    /// instance `gid` or `ogid`
+    pub fn resolved_group(&self, field: &str) -> Option<String> {
+        resolve_gid(self.id_field(field)?)
+    }
+
+    fn id_field(&self, field: &str) -> Option<u32> {
+        u32::try_from(self.field(field)?.as_u64()?)
+            .ok()
+            .filter(|id| *id != UNSET_ID)
+    }
+}
+
+fn cached<F>(
+    cache: &Mutex<Option<HashMap<u32, Option<String>>>>,
+    id: u32,
+    lookup: F,
+) -> Option<String>
+where
+    F: FnOnce() -> Option<String>,
+{
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get_or_insert_with(HashMap::new)
+        .entry(id)
+        .or_insert_with(lookup)
+        .clone()
+}
+
+/// Call a `get*id_r` function, growing the buffer while it returns `ERANGE`
+fn with_buffer<F>(mut call: F) -> Option<String>
+where
+    F: FnMut(&mut [c_char]) -> Result<Option<*const c_char>, i32>,
+{
+    let mut buf = vec![0 as c_char; 1024];
+    loop {
+        match call(&mut buf) {
+            Ok(name) => {
+                // SAFETY: the name points to a NUL terminated string in buf
+                return name.map(|name| unsafe {
+                    CStr::from_ptr(name).to_string_lossy().into_owned()
+                });
+            }
+            Err(libc::ERANGE) if buf.len() < 1 << 20 => {
+                buf.resize(buf.len() * 2, 0);
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
+fn lookup_user(uid: u32) -> Option<String> {
+    with_buffer(|buf| {
+        let mut pwd = MaybeUninit::<libc::passwd>::uninit();
+        let mut result = std::ptr::null_mut();
+        // SAFETY: all the pointers are valid, and the size of buf is passed
+        // along
+        let res = unsafe {
+            libc::getpwuid_r(
+                uid,
+                pwd.as_mut_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            )
+        };
+        match res {
+            0 if result.is_null() => Ok(None),
+            // SAFETY: on success, result points to the initialized pwd
+            0 => Ok(Some(unsafe { (*result).pw_name } as *const c_char)),
+            err => Err(err),
+        }
+    })
+}
+
+fn lookup_group(gid: u32) -> Option<String> {
+    with_buffer(|buf| {
+        let mut grp = MaybeUninit::<libc::group>::uninit();
+        let mut result = std::ptr::null_mut();
+        // SAFETY: all the pointers are valid, and the size of buf is passed
+        // along
+        let res = unsafe {
+            libc::getgrgid_r(
+                gid,
+                grp.as_mut_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            )
+        };
+        match res {
+            0 if result.is_null() => Ok(None),
+            // SAFETY: on success, result points to the initialized grp
+            0 => Ok(Some(unsafe { (*result).gr_name } as *const c_char)),
+            err => Err(err),
+        }
+    })
+}
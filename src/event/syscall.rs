@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    event::{args::decode_args, path::number, AuditEvent, FieldValue},
+    packet::constants::AUDIT_SYSCALL,
+    syscalls,
+};
+
+/// The syscall that triggered an event, from an `AUDIT_SYSCALL` record.
+///
+/// `arch`, `syscall` and the arguments are always present. The kernel omits
+/// `success` and `exit` when the record is emitted before the syscall
+/// returns, for instance for `execve` rules on the entry filter list.
+/// Unset ids, such as the `auid` of daemons, are `u32::MAX`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyscallRecord {
+    /// `AUDIT_ARCH_*` value of the syscall
+    pub arch: u32,
+    /// Number of the syscall, see [`SyscallRecord::syscall_name`]
+    pub syscall: u32,
+    pub success: Option<bool>,
+    /// Return value of the syscall, a negative errno on failure
+    pub exit: Option<i64>,
+    /// The first four arguments of the syscall
+    pub args: [u64; 4],
+    /// Number of `AUDIT_PATH` records in the event
+    pub items: Option<u32>,
+    pub ppid: Option<u32>,
+    pub pid: Option<u32>,
+    pub auid: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub euid: Option<u32>,
+    pub suid: Option<u32>,
+    pub fsuid: Option<u32>,
+    /// Command name of the process. Invalid UTF-8 sequences are replaced
+    /// with `U+FFFD`.
+    pub comm: Option<String>,
+    /// Path of the executable. Invalid UTF-8 sequences are replaced with
+    /// `U+FFFD`.
+    pub exe: Option<String>,
+    /// Key of the rule that matched, if it has one
+    pub key: Option<String>,
+}
+
+impl SyscallRecord {
+    /// Return `None` if the record is not a valid `AUDIT_SYSCALL` record
+    pub fn from_record(record: &AuditEvent) -> Option<Self> {
+        if record.record_type != AUDIT_SYSCALL {
+            return None;
+        }
+        // arch and the arguments are hexadecimal, without any prefix
+        let hex = |name: &str| record.field(name)?.as_str();
+        let arg = |name: &str| u64::from_str_radix(hex(name)?, 16).ok();
+        let string = |name: &str| {
+            record
+                .field(name)
+                .filter(|value| value.as_str() != Some("(null)"))
+                .and_then(FieldValue::as_bytes)
+                .map(|value| String::from_utf8_lossy(value).into_owned())
+        };
+
+        Some(SyscallRecord {
+            arch: u32::from_str_radix(hex("arch")?, 16).ok()?,
+            syscall: number(record.field("syscall"))?,
+            success: record.field("success").and_then(FieldValue::as_bool),
+            exit: record.field("exit").and_then(FieldValue::as_i64),
+            args: [arg("a0")?, arg("a1")?, arg("a2")?, arg("a3")?],
+            items: number(record.field("items")),
+            ppid: number(record.field("ppid")),
+            pid: number(record.field("pid")),
+            auid: number(record.field("auid")),
+            uid: number(record.field("uid")),
+            gid: number(record.field("gid")),
+            euid: number(record.field("euid")),
+            suid: number(record.field("suid")),
+            fsuid: number(record.field("fsuid")),
+            comm: string("comm"),
+            exe: string("exe"),
+            key: string("key"),
+        })
+    }
+
+    /// Return the name of the syscall, if the architecture is supported by
+    /// [`syscalls`]
+    pub fn syscall_name(&self) -> Option<&'static str> {
+        syscalls::name(self.arch, self.syscall)
+    }
+
+    /// Return the argument `n`, from 0 to 3. The kernel does not log the
+    /// following ones.
+    pub fn arg(&self, n: usize) -> Option<u64> {
+        self.args.get(n).copied()
+    }
+
+    /// Interpret the arguments of well-known syscalls, as `ausearch -i`
+    /// does: the flags of `open` and `openat`, the protection and flags of
+    /// `mmap` and `mprotect`. Return `None` for other syscalls, or
+    /// architectures other than x86_64 and aarch64, whose flags differ.
+    ///
+    /// Arguments that are not interpreted, such as pointers, are rendered in
+    /// hexadecimal, like in the record.
+    ///
+    /// ```
+    /// use audit::{packet::constants::AUDIT_SYSCALL, SyscallRecord};
+    ///
+    /// let mut record = audit::parse_event(
+    ///     b"audit(1699999999.123:4567): arch=c000003e syscall=257 \
+    ///       success=yes exit=3 a0=ffffff9c a1=7ffd2c a2=80241 a3=1a4",
+    /// )
+    /// .unwrap();
+    /// record.record_type = AUDIT_SYSCALL;
+    /// let syscall = SyscallRecord::from_record(&record).unwrap();
+    /// assert_eq!(syscall.arg(2), Some(0x80241));
+    /// assert_eq!(
+    ///     syscall.decode_args().unwrap(),
+    ///     ["AT_FDCWD", "7ffd2c", "O_WRONLY|O_CREAT|O_TRUNC|O_CLOEXEC", "0644"],
+    /// );
+    /// ```
+    pub fn decode_args(&self) -> Option<[String; 4]> {
+        decode_args(self.arch, self.syscall_name()?, &self.args)
+    }
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MIT
+
+use std::convert::TryInto;
+
+use crate::packet::constants::AUDIT_FEATURE_VERSION;
+
+const FEATURES_LEN: usize = 16;
+
+/// Kernel audit features, as exchanged with `AUDIT_GET_FEATURE` and
+/// `AUDIT_SET_FEATURE` messages (`struct audit_features`).
+///
+/// Each feature is identified by its index (for instance
+/// `AUDIT_FEATURE_LOGINUID_IMMUTABLE`), and is represented by the
+/// corresponding bit in `mask`, `features` and `lock`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Features {
+    /// Version of the structure, should be `AUDIT_FEATURE_VERSION`
+    pub version: u32,
+    /// Bit mask of the features this message is about
+    pub mask: u32,
+    /// Bit mask of the enabled features
+    pub features: u32,
+    /// Bit mask of the features that cannot be changed anymore
+    pub lock: u32,
+}
+
+impl Features {
+    pub fn new() -> Self {
+        Features {
+            version: AUDIT_FEATURE_VERSION,
+            ..Default::default()
+        }
+    }
+
+    /// Return `true` if the given feature is enabled
+    pub fn is_enabled(&self, feature: u32) -> bool {
+        let mask = feature_to_mask(feature);
+        mask != 0 && self.features & mask == mask
+    }
+
+    /// Return `true` if the given feature is locked
+    pub fn is_locked(&self, feature: u32) -> bool {
+        let mask = feature_to_mask(feature);
+        mask != 0 && self.lock & mask == mask
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(FEATURES_LEN);
+        bytes.extend_from_slice(&self.version.to_ne_bytes());
+        bytes.extend_from_slice(&self.mask.to_ne_bytes());
+        bytes.extend_from_slice(&self.features.to_ne_bytes());
+        bytes.extend_from_slice(&self.lock.to_ne_bytes());
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < FEATURES_LEN {
+            return None;
+        }
+        let read = |i: usize| {
+            u32::from_ne_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap())
+        };
+        Some(Features {
+            version: read(0),
+            mask: read(1),
+            features: read(2),
+            lock: read(3),
+        })
+    }
+}
+
+pub(crate) fn feature_to_mask(feature: u32) -> u32 {
+    1u32.checked_shl(feature).unwrap_or(0)
+}
@@ -1,10 +1,17 @@
 // SPDX-License-Identifier: MIT
 
-use std::process;
+use std::{
+    convert::TryFrom,
+    future::Future,
+    process,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use futures::{
+    channel::mpsc::{Receiver, UnboundedReceiver},
     future::{self, Either},
-    stream::{Stream, StreamExt, TryStream},
+    stream::{Stream, StreamExt, TryStream, TryStreamExt},
     FutureExt,
 };
 use netlink_packet_core::{
@@ -13,7 +20,15 @@ use netlink_packet_core::{
 };
 use netlink_proto::{sys::SocketAddr, ConnectionHandle};
 
-use crate::packet::{rules::RuleMessage, AuditMessage, StatusMessage};
+use crate::packet::{
+    constants::{
+        AUDIT_FIRST_USER_MSG, AUDIT_GET_FEATURE, AUDIT_LAST_FEATURE,
+        AUDIT_MAKE_EQUIV, AUDIT_SET_FEATURE, AUDIT_TRIM, AUDIT_TTY_GET,
+        AUDIT_TTY_SET,
+    },
+    rules::{RuleField, RuleFieldFlags, RuleFlags, RuleMessage},
+    AuditMessage, StatusMessage,
+};
 
 // ==========================================
 // mask values
@@ -33,30 +48,240 @@ pub const AUDIT_FEATURE_BITMAP_SESSIONID_FILTER: u32 = 16;
 pub const AUDIT_FEATURE_BITMAP_LOST_RESET: u32 = 32;
 pub const AUDIT_FEATURE_BITMAP_FILTER_FS: u32 = 64;
 pub const AUDIT_FEATURE_BITMAP_ALL: u32 = 127;
+// Older names of the feature bits, from when `feature_bitmap` was called
+// `version`. The kernel still reports the same field, see
+// `Handle::get_version`.
+/// All the features known to this crate, see
+/// [`AUDIT_FEATURE_BITMAP_ALL`]
 pub const AUDIT_VERSION_LATEST: u32 = 127;
+/// The kernel supports `backlog_limit`, see
+/// [`AUDIT_FEATURE_BITMAP_BACKLOG_LIMIT`]
 pub const AUDIT_VERSION_BACKLOG_LIMIT: u32 = 1;
+/// The kernel supports `backlog_wait_time`, see
+/// [`AUDIT_FEATURE_BITMAP_BACKLOG_WAIT_TIME`]
 pub const AUDIT_VERSION_BACKLOG_WAIT_TIME: u32 = 2;
 
-use crate::Error;
+use crate::{
+    event::bounded, feature_to_mask, time::TimeoutStream, AuditEvent,
+    AuditMetrics, DropCounter, Error, FailureMode, Features, PidRegistration,
+    RuleMessageExt, RulesPlan, StatusMessageBuilder, StatusMessageExt,
+    TtyStatus, UserRecord,
+};
+
+/// Receiver of the unsolicited messages of a connection, as returned by
+/// `new_connection`
+type Messages = UnboundedReceiver<(NetlinkMessage<AuditMessage>, SocketAddr)>;
 
 /// A handle to the netlink connection, used to send and receive netlink
 /// messsage
 #[derive(Clone, Debug)]
-pub struct Handle(ConnectionHandle<AuditMessage>);
+pub struct Handle {
+    // Shared by the clones of the handle, so that `ReconnectingConnection`
+    // can swap it for the handle of a new connection
+    handle: Arc<Mutex<ConnectionHandle<AuditMessage>>>,
+    timeout: Option<Duration>,
+    // Shared by the clones of the handle, the first call to `events` takes
+    // it
+    messages: Arc<Mutex<Option<Messages>>>,
+}
 
 impl Handle {
     pub(crate) fn new(conn: ConnectionHandle<AuditMessage>) -> Self {
-        Handle(conn)
+        Handle {
+            handle: Arc::new(Mutex::new(conn)),
+            timeout: None,
+            messages: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Send the requests of this handle and its clones to another
+    /// connection
+    pub(crate) fn set_connection(&self, other: Handle) {
+        let conn = other
+            .handle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        *self.handle.lock().unwrap_or_else(|e| e.into_inner()) = conn;
+    }
+
+    /// Give the handle the unsolicited messages receiver returned by
+    /// `new_connection`, so that `Handle::events` can be used.
+    ///
+    /// ```no_run
+    /// use audit::new_connection;
+    /// use futures::stream::TryStreamExt;
+    ///
+    /// # async fn run() -> Result<(), audit::Error> {
+    /// let (connection, handle, messages) = new_connection().unwrap();
+    /// tokio::spawn(connection);
+    /// let mut handle = handle.with_messages(messages);
+    ///
+    /// handle.enable_events().await?;
+    /// let mut events = handle.events()?;
+    /// while let Some(event) = events.try_next().await? {
+    ///     println!("{:?}", event);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_messages(self, messages: Messages) -> Self {
+        *self.messages.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(messages);
+        self
+    }
+
+    /// Return the stream of the audit records received by this process,
+    /// parsed into [`AuditEvent`]s. Messages that are not audit records are
+    /// skipped.
+    ///
+    /// This requires the receiver given to `Handle::with_messages`, and can
+    /// only be called once: it fails with [`Error::EventsUnavailable`]
+    /// otherwise. The records are only sent by the kernel once
+    /// `Handle::enable_events` was called.
+    ///
+    /// If the process does not read the records fast enough, the socket
+    /// buffer fills up and the kernel drops the records that do not fit. The
+    /// stream then yields [`Error::Overrun`] and carries on with the next
+    /// records. The `lost` counter of `Handle::get_status` tells how many
+    /// records the kernel dropped overall, and `ConnectionBuilder::rcvbuf`
+    /// makes overruns less likely.
+    pub fn events(
+        &mut self,
+    ) -> Result<impl Stream<Item = Result<AuditEvent, Error>>, Error> {
+        let messages = self.take_messages()?;
+        Ok(Self::parse_events(messages, |_| true))
+    }
+
+    /// Like `Handle::events`, but deliver the events through a channel
+    /// holding at most `capacity + 1` events, for predictable memory use
+    /// under event storms.
+    ///
+    /// The returned future forwards the events to the channel and must be
+    /// spawned. When the channel is full, events are dropped and counted by
+    /// the returned [`DropCounter`]: the kernel cannot be slowed down, since
+    /// the connection keeps reading the socket. If the connection itself
+    /// does not read fast enough, the socket buffer overflows and the
+    /// channel receives [`Error::Overrun`] instead, if it has room. The
+    /// future completes when the receiver is dropped or the connection
+    /// closes.
+    #[allow(clippy::type_complexity)]
+    pub fn event_channel(
+        &mut self,
+        capacity: usize,
+    ) -> Result<
+        (
+            impl Future<Output = ()>,
+            Receiver<Result<AuditEvent, Error>>,
+            DropCounter,
+        ),
+        Error,
+    > {
+        Ok(bounded(self.events()?, capacity))
+    }
+
+    /// Call `Handle::enable_events`, and return the stream of
+    /// `Handle::events`, restricted to the records whose type is one of
+    /// `types`, for instance `&[AUDIT_SYSCALL, AUDIT_EXECVE]`.
+    ///
+    /// The kernel still sends all the records to this process: the other
+    /// records are discarded before being parsed, like
+    /// [`filter_record_types`](crate::filter_record_types) does. Overruns
+    /// are reported as in `Handle::events`.
+    pub async fn enable_events_filtered(
+        &mut self,
+        types: &[u16],
+    ) -> Result<impl Stream<Item = Result<AuditEvent, Error>>, Error> {
+        let messages = self.take_messages()?;
+        if let Err(e) = self.enable_events().await {
+            // Give the receiver back, so that the caller can try again
+            *self.messages.lock().unwrap_or_else(|e| e.into_inner()) =
+                Some(messages);
+            return Err(e);
+        }
+        let types = types.to_vec();
+        Ok(Self::parse_events(messages, move |message_type| {
+            types.contains(&message_type)
+        }))
+    }
+
+    /// Return `true` if `Handle::events` and its variants can still be
+    /// called
+    #[cfg(feature = "tokio_socket")]
+    pub(crate) fn has_messages(&self) -> bool {
+        self.messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    fn take_messages(&self) -> Result<Messages, Error> {
+        self.messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .ok_or(Error::EventsUnavailable)
+    }
+
+    fn parse_events<F>(
+        messages: Messages,
+        keep: F,
+    ) -> impl Stream<Item = Result<AuditEvent, Error>>
+    where
+        F: Fn(u16) -> bool,
+    {
+        messages.filter_map(move |(message, _)| {
+            future::ready(match message.payload {
+                NetlinkPayload::InnerMessage(ref message)
+                    if message.message_type() >= AUDIT_FIRST_USER_MSG
+                        && keep(message.message_type()) =>
+                {
+                    Some(AuditEvent::try_from(message).map_err(Error::from))
+                }
+                NetlinkPayload::Overrun(_) => Some(Err(Error::Overrun)),
+                _ => None,
+            })
+        })
+    }
+
+    /// Make the requests sent by this handle fail with [`Error::Timeout`]
+    /// if the kernel does not reply within `timeout`. For dumps such as
+    /// `Handle::list_rules`, the timeout applies to each message.
+    ///
+    /// By default, requests wait forever. The timer is provided by the
+    /// runtime selected with the `tokio_socket` or `smol_socket` feature:
+    /// without any of them, requests never time out.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
     }
 
     /// Send a netlink message, and get the reponse as a stream of messages.
+    ///
+    /// The timeout set with `Handle::with_timeout` does not apply to the
+    /// returned stream.
     pub fn request(
         &mut self,
         message: NetlinkMessage<AuditMessage>,
     ) -> Result<impl Stream<Item = NetlinkMessage<AuditMessage>>, Error> {
-        self.0
+        self.handle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
             .request(message, SocketAddr::new(0, 0))
-            .map_err(|_| Error::RequestFailed)
+            .map_err(Error::from)
+    }
+
+    /// Same as `Handle::request`, but the messages are wrapped into a
+    /// `Result` that is an error if the handle's timeout expires.
+    fn timed_request(
+        &mut self,
+        message: NetlinkMessage<AuditMessage>,
+    ) -> Result<
+        impl Stream<Item = Result<NetlinkMessage<AuditMessage>, Error>>,
+        Error,
+    > {
+        let timeout = self.timeout;
+        Ok(TimeoutStream::new(self.request(message)?, timeout))
     }
 
     /// Send a netlink message that expects an acknowledgement. The returned
@@ -66,13 +291,22 @@ impl Handle {
         &mut self,
         message: NetlinkMessage<AuditMessage>,
     ) -> Result<(), Error> {
-        let mut response = self.request(message)?;
-        if let Some(message) = response.next().await {
+        let response = self.timed_request(message)?;
+        Self::wait_ack(response).await
+    }
+
+    /// Wait for the acknowledgement of a request sent with
+    /// `Handle::timed_request`
+    async fn wait_ack<S>(mut response: S) -> Result<(), Error>
+    where
+        S: Stream<Item = Result<NetlinkMessage<AuditMessage>, Error>> + Unpin,
+    {
+        if let Some(message) = response.next().await.transpose()? {
             let (header, payload) = message.into_parts();
             // NetlinkError and AuditMessage are forwarded to the
             // handle. Ack is signaled by the stream finishing.
             if let NetlinkPayload::Error(err_msg) = payload {
-                Err(Error::NetlinkError(err_msg))
+                Err(Error::from(err_msg))
             } else {
                 Err(Error::UnexpectedMessage(NetlinkMessage::new(
                     header, payload,
@@ -83,12 +317,236 @@ impl Handle {
         }
     }
 
-    /// Add the given rule
+    /// Send a message that netlink-packet-audit does not have a dedicated
+    /// type for, and parse the raw payload of the reply with `parse`.
+    async fn raw_request<T, F>(
+        &mut self,
+        message_type: u16,
+        data: String,
+        parse: F,
+    ) -> Result<T, Error>
+    where
+        F: FnOnce(&[u8]) -> Option<T>,
+    {
+        let mut req =
+            NetlinkMessage::from(AuditMessage::Other((message_type, data)));
+        req.header.flags = NLM_F_REQUEST;
+        let mut request = self.timed_request(req)?;
+
+        let response = request
+            .next()
+            .await
+            .transpose()?
+            .ok_or(Error::RequestFailed)?;
+
+        let (header, payload) = response.into_parts();
+        if let NetlinkPayload::InnerMessage(AuditMessage::Other((
+            reply_type,
+            ref data,
+        ))) = payload
+        {
+            if reply_type == message_type {
+                if let Some(reply) = parse(data.as_bytes()) {
+                    return Ok(reply);
+                }
+            }
+        }
+        match payload {
+            NetlinkPayload::Error(err_msg) => Err(Error::from(err_msg)),
+            payload => Err(Error::UnexpectedMessage(NetlinkMessage::new(
+                header, payload,
+            ))),
+        }
+    }
+
+    /// Remove the watch rules whose directory does not exist anymore (like
+    /// `auditctl -t`)
+    pub async fn trim(&mut self) -> Result<(), Error> {
+        let mut req = NetlinkMessage::from(AuditMessage::Other((
+            AUDIT_TRIM,
+            String::new(),
+        )));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
+    /// Make the watches on `new` behave as if they were on `old`, for
+    /// instance when `new` is a bind mount of `old` (like `auditctl -m`).
+    ///
+    /// The paths are sent as two native endian `u32` lengths followed by the
+    /// non NUL terminated strings. The payload must be valid UTF-8, so every
+    /// byte of the lengths must be below `0x80`: this fails with
+    /// [`Error::UnsupportedPayload`], without sending anything, if a path is
+    /// 128 bytes or longer.
+    pub async fn make_equiv(
+        &mut self,
+        old: &str,
+        new: &str,
+    ) -> Result<(), Error> {
+        if old.len() >= 0x80 || new.len() >= 0x80 {
+            return Err(Error::UnsupportedPayload(AUDIT_MAKE_EQUIV));
+        }
+        let mut data = Vec::with_capacity(8 + old.len() + new.len());
+        data.extend_from_slice(&(old.len() as u32).to_ne_bytes());
+        data.extend_from_slice(&(new.len() as u32).to_ne_bytes());
+        data.extend_from_slice(old.as_bytes());
+        data.extend_from_slice(new.as_bytes());
+        let data = String::from_utf8(data)
+            .map_err(|_| Error::UnsupportedPayload(AUDIT_MAKE_EQUIV))?;
+
+        let mut req =
+            NetlinkMessage::from(AuditMessage::Other((AUDIT_MAKE_EQUIV, data)));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
+    /// Add the given rule. The rule is checked with
+    /// [`RuleMessageExt::validate`](crate::RuleMessageExt::validate) first.
+    ///
+    /// The fields are sent in the order of `rule.fields`: the kernel accepts
+    /// `RuleField::Arch` anywhere in the rule, even after fields such as
+    /// `RuleField::Exit`. Only the syscall numbers depend on the
+    /// architecture, see [`parse_rule`](crate::parse_rule).
+    ///
+    /// Watches are rules too, with a `RuleField::Watch` or `RuleField::Dir`
+    /// field. The `AUDIT_WATCH_INS`, `AUDIT_WATCH_REM` and
+    /// `AUDIT_WATCH_LIST` messages of some older vendor kernels are not
+    /// supported: mainline kernels never implemented them, and reject them
+    /// with `EINVAL`.
     pub async fn add_rule(&mut self, rule: RuleMessage) -> Result<(), Error> {
+        rule.validate()?;
+        self.send_rule(rule).await
+    }
+
+    /// Add a rule like `Handle::add_rule`, without validating it, for
+    /// instance to restore a rule listed by the kernel
+    async fn send_rule(&mut self, rule: RuleMessage) -> Result<(), Error> {
         let mut req = NetlinkMessage::from(AuditMessage::AddRule(rule));
         req.header.flags =
             NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL | NLM_F_CREATE;
-        self.acked_request(req).await
+        self.acked_request(req).await.map_err(Error::for_rule)
+    }
+
+    /// Add the given rule, unless an equivalent rule is already loaded, and
+    /// return whether it was added. Unlike `Handle::add_rule`, this succeeds
+    /// if the rule exists: configuration management tools can call it
+    /// every time they apply their rules.
+    ///
+    /// Rules are compared with
+    /// [`RuleMessageExt::is_equivalent`](crate::RuleMessageExt::is_equivalent)
+    /// rather than `==`, since the kernel may list the fields in another
+    /// order. See also `Handle::ensure_absent`.
+    pub async fn ensure_rule(
+        &mut self,
+        rule: RuleMessage,
+    ) -> Result<bool, Error> {
+        rule.validate()?;
+        let exists = self
+            .list_rules()
+            .try_filter(|listed| future::ready(listed.is_equivalent(&rule)))
+            .try_next()
+            .await?
+            .is_some();
+        if exists {
+            return Ok(false);
+        }
+        match self.add_rule(rule).await {
+            Ok(()) => Ok(true),
+            // Added by another process in the meantime
+            Err(Error::RuleExists) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Add the given rule like `Handle::add_rule`, and return the rule as the
+    /// kernel stored it. The kernel may reorder the fields or drop the ones
+    /// it does not support, so the returned rule is the one to compare with
+    /// the output of `Handle::list_rules`.
+    ///
+    /// The rules are listed before and after adding the rule, and the rule
+    /// that appeared is returned, so this is slower than `Handle::add_rule`.
+    /// If other processes add rules at the same time, a rule equivalent to
+    /// `rule` is preferred. Return [`Error::RuleNotListed`] if no rule
+    /// appeared.
+    pub async fn add_rule_checked(
+        &mut self,
+        rule: RuleMessage,
+    ) -> Result<RuleMessage, Error> {
+        rule.validate()?;
+        let mut before: Vec<RuleMessage> =
+            self.list_rules().try_collect().await?;
+        self.add_rule(rule.clone()).await?;
+        let after: Vec<RuleMessage> = self.list_rules().try_collect().await?;
+
+        let mut added = Vec::new();
+        for listed in after {
+            match before.iter().position(|r| *r == listed) {
+                Some(index) => {
+                    before.swap_remove(index);
+                }
+                None => added.push(listed),
+            }
+        }
+        match added.iter().position(|r| r.is_equivalent(&rule)) {
+            Some(index) => Ok(added.swap_remove(index)),
+            None => added.into_iter().next().ok_or(Error::RuleNotListed),
+        }
+    }
+
+    /// Add several rules, sending all of them before waiting for the
+    /// acknowledgements of the kernel. This is much faster than calling
+    /// `Handle::add_rule` for each rule.
+    ///
+    /// All the rules are validated before any is sent. If a rule fails, the
+    /// other rules are still added, and [`Error::RuleFailed`] is returned
+    /// with the index of the first rule that failed.
+    pub async fn add_rules(
+        &mut self,
+        rules: Vec<RuleMessage>,
+    ) -> Result<(), Error> {
+        let failed = |index, error| Error::RuleFailed {
+            index,
+            error: Box::new(error),
+        };
+        for (index, rule) in rules.iter().enumerate() {
+            rule.validate().map_err(|e| failed(index, e))?;
+        }
+        self.send_rules(rules).await
+    }
+
+    /// Add several rules like `Handle::add_rules`, without validating them,
+    /// for instance to restore rules listed by the kernel
+    async fn send_rules(
+        &mut self,
+        rules: Vec<RuleMessage>,
+    ) -> Result<(), Error> {
+        let failed = |index, error| Error::RuleFailed {
+            index,
+            error: Box::new(error),
+        };
+        let mut responses = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let mut req = NetlinkMessage::from(AuditMessage::AddRule(rule));
+            req.header.flags =
+                NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL | NLM_F_CREATE;
+            responses.push(self.timed_request(req));
+        }
+
+        let mut result = Ok(());
+        for (index, response) in responses.into_iter().enumerate() {
+            let ack = match response {
+                Ok(response) => {
+                    Self::wait_ack(response).await.map_err(Error::for_rule)
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = ack {
+                if result.is_ok() {
+                    result = Err(failed(index, e));
+                }
+            }
+        }
+        result
     }
 
     /// Deletes a given rule
@@ -98,23 +556,136 @@ impl Handle {
         self.acked_request(req).await
     }
 
+    /// Delete the loaded rule that is equivalent to `rule`, if any, and
+    /// return whether a rule was deleted. This is the inverse of
+    /// `Handle::ensure_rule`.
+    ///
+    /// The kernel only deletes rules whose fields are in the same order, so
+    /// the rule is deleted as it is listed rather than as given.
+    pub async fn ensure_absent(
+        &mut self,
+        rule: &RuleMessage,
+    ) -> Result<bool, Error> {
+        let listed = self
+            .list_rules()
+            .try_filter(|listed| future::ready(listed.is_equivalent(rule)))
+            .try_next()
+            .await?;
+        let listed = match listed {
+            Some(listed) => listed,
+            None => return Ok(false),
+        };
+        match self.del_rule(listed).await {
+            Ok(()) => Ok(true),
+            // Deleted by another process in the meantime
+            Err(e) if e.errno() == Some(libc::ENOENT) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace the rule `old` with `new`.
+    ///
+    /// The kernel has no way to update a rule, so `old` is deleted before
+    /// `new` is added: there is a short window during which neither is
+    /// loaded. If adding `new` fails, `old` is added back as it is, without
+    /// validating it, and the error is returned. If `old` cannot be added
+    /// back either, [`Error::RollbackFailed`] is returned with both errors.
+    pub async fn replace_rule(
+        &mut self,
+        old: RuleMessage,
+        new: RuleMessage,
+    ) -> Result<(), Error> {
+        new.validate()?;
+        self.del_rule(old.clone()).await?;
+        if let Err(error) = self.send_rule(new).await {
+            return Err(match self.send_rule(old).await {
+                Ok(()) => error,
+                Err(rollback) => Error::RollbackFailed {
+                    error: Box::new(error),
+                    rollback: Box::new(rollback),
+                },
+            });
+        }
+        Ok(())
+    }
+
+    /// Change the permissions (`AUDIT_PERM_*` bits) of the watch rules on
+    /// `path`, like `auditctl -w <path> -p <perm>` on an existing watch.
+    ///
+    /// The kernel has no way to update a rule, and ignores `NLM_F_REPLACE`
+    /// on `AUDIT_ADD_RULE`. However, it treats watches that only differ by
+    /// their permissions as different rules, so the updated watch is added
+    /// before the old one is deleted: the path is watched at all times, and
+    /// may briefly be watched with both permissions. If deleting the old
+    /// watch fails, the new one is deleted and the error is returned.
+    ///
+    /// Return [`Error::InvalidRule`] if there is no watch on `path`.
+    pub async fn update_watch_perms(
+        &mut self,
+        path: &str,
+        perm: u32,
+    ) -> Result<(), Error> {
+        let is_watch = |field: &RuleField| match field {
+            RuleField::Watch(p) | RuleField::Dir(p) => p == path,
+            _ => false,
+        };
+        let watches: Vec<RuleMessage> = self
+            .list_rules()
+            .try_filter(|rule| {
+                future::ready(rule.fields.iter().any(|(f, _)| is_watch(f)))
+            })
+            .try_collect()
+            .await?;
+        if watches.is_empty() {
+            return Err(Error::InvalidRule(format!("no watch on {path}")));
+        }
+
+        for old in watches {
+            let mut new = old.clone();
+            new.fields
+                .retain(|(field, _)| !matches!(field, RuleField::Perm(_)));
+            new.fields
+                .push((RuleField::Perm(perm), RuleFieldFlags::Equal));
+            if new == old {
+                continue;
+            }
+            self.add_rule(new.clone()).await?;
+            if let Err(e) = self.del_rule(old).await {
+                let _ = self.del_rule(new).await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     /// List the current rules
+    ///
+    /// The stream can be dropped before the end of the dump, for instance
+    /// once the rule of interest is found. The remaining messages of the
+    /// dump are matched with this request by their sequence number and
+    /// discarded by the connection as they arrive: they are never received
+    /// by the following requests.
+    ///
+    /// Rules that `netlink-packet-audit` cannot parse, for instance because
+    /// they use a field added by a newer kernel, are dropped by the codec
+    /// with an error log, before reaching the stream: they are not listed,
+    /// and cannot be deleted by `Handle::delete_all_rules`. Comparing the
+    /// number of listed rules with the output of `auditctl -l` reveals
+    /// them.
     pub fn list_rules(
         &mut self,
     ) -> impl TryStream<Ok = RuleMessage, Error = Error> {
         let mut req = NetlinkMessage::from(AuditMessage::ListRules(None));
         req.header.flags = NLM_F_REQUEST | NLM_F_DUMP;
 
-        match self.request(req) {
+        match self.timed_request(req) {
             Ok(response) => Either::Left(response.map(move |msg| {
-                let (header, payload) = msg.into_parts();
+                let (header, payload) = msg?.into_parts();
                 match payload {
                     NetlinkPayload::InnerMessage(AuditMessage::ListRules(
                         Some(rule_msg),
                     )) => Ok(rule_msg),
-                    NetlinkPayload::Error(err_msg) => {
-                        Err(Error::NetlinkError(err_msg))
-                    }
+                    NetlinkPayload::Error(err_msg) => Err(Error::from(err_msg)),
                     _ => Err(Error::UnexpectedMessage(NetlinkMessage::new(
                         header, payload,
                     ))),
@@ -126,6 +697,145 @@ impl Handle {
         }
     }
 
+    /// List the current rules having the given key, like `auditctl -l -k`.
+    /// The kernel has no way to filter rules, so they are filtered as they
+    /// are received.
+    pub fn list_rules_by_key(
+        &mut self,
+        key: &str,
+    ) -> impl TryStream<Ok = RuleMessage, Error = Error> {
+        let key = key.to_string();
+        self.list_rules().try_filter(move |rule| {
+            future::ready(rule.keys().contains(&key.as_str()))
+        })
+    }
+
+    /// List the current rules of the given filter list, such as
+    /// `RuleFlags::FilterExit`. Like `Handle::list_rules_by_key`, rules are
+    /// filtered as they are received.
+    pub fn list_rules_by_filter(
+        &mut self,
+        flags: RuleFlags,
+    ) -> impl TryStream<Ok = RuleMessage, Error = Error> {
+        self.list_rules()
+            .try_filter(move |rule| future::ready(rule.flags == flags))
+    }
+
+    /// List the current rules, like `Handle::list_rules`, collected into a
+    /// `Vec`
+    pub async fn list_rules_vec(&mut self) -> Result<Vec<RuleMessage>, Error> {
+        self.list_rules().try_collect().await
+    }
+
+    /// Delete all the rules, like `auditctl -D`, and return the number of
+    /// rules that were deleted.
+    ///
+    /// Failing to delete a rule does not stop the deletion of the remaining
+    /// ones.
+    pub async fn delete_all_rules(&mut self) -> Result<usize, Error> {
+        let rules = self.list_rules_vec().await?;
+        let mut deleted = 0;
+        for rule in rules {
+            if self.del_rule(rule).await.is_ok() {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Replace all the rules with `rules`, for instance to deploy a new
+    /// version of a policy.
+    ///
+    /// The current rules are listed, deleted, and the new rules are added
+    /// with `Handle::add_rules`. If a current rule cannot be deleted or a
+    /// new rule fails, the rules are deleted again, the previous rules are
+    /// restored as they were listed, and the first error is returned. If
+    /// the rollback fails too, [`Error::RollbackFailed`] is returned with
+    /// both errors: list the rules to check what is in place.
+    ///
+    /// The kernel has no way to swap rulesets: between the deletion and the
+    /// addition of the rules, fewer rules are active, and events may not be
+    /// recorded. Rules are validated before anything is deleted.
+    pub async fn reload_rules(
+        &mut self,
+        rules: Vec<RuleMessage>,
+    ) -> Result<(), Error> {
+        for (index, rule) in rules.iter().enumerate() {
+            rule.validate().map_err(|e| Error::RuleFailed {
+                index,
+                error: Box::new(e),
+            })?;
+        }
+        let previous = self.list_rules_vec().await?;
+        let mut result = Ok(());
+        for rule in previous.iter() {
+            if let Err(e) = self.del_rule(rule.clone()).await {
+                result = Err(e);
+                break;
+            }
+        }
+        if result.is_ok() {
+            result = self.add_rules(rules).await;
+        }
+        if let Err(error) = result {
+            // Restore the previous rules even if the cleanup fails. They
+            // come from the kernel, so they are not validated again.
+            let cleanup = self.delete_all_rules().await.map(drop);
+            let restore = self.send_rules(previous).await;
+            return Err(match restore.and(cleanup) {
+                Ok(()) => error,
+                Err(rollback) => Error::RollbackFailed {
+                    error: Box::new(error),
+                    rollback: Box::new(rollback),
+                },
+            });
+        }
+        Ok(())
+    }
+
+    /// Delete the rules having the given key, like `auditctl -D -k`, and
+    /// return the number of rules that were deleted. Rules with other keys
+    /// are left alone, so that several tools can manage their own rules.
+    ///
+    /// Like `Handle::delete_all_rules`, failing to delete a rule does not
+    /// stop the deletion of the remaining ones.
+    pub async fn delete_rules_by_key(
+        &mut self,
+        key: &str,
+    ) -> Result<usize, Error> {
+        let rules: Vec<RuleMessage> =
+            self.list_rules_by_key(key).try_collect().await?;
+        let mut deleted = 0;
+        for rule in rules {
+            if self.del_rule(rule).await.is_ok() {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Apply a [`RulesPlan`] loaded with
+    /// [`load_rules_dir`](crate::load_rules_dir), in the order auditctl
+    /// uses: delete the rules if asked to, change the status, add the rules
+    /// and finally enable, disable or lock auditing.
+    pub async fn apply_rules_plan(
+        &mut self,
+        plan: RulesPlan,
+    ) -> Result<(), Error> {
+        if plan.delete_all {
+            self.delete_all_rules().await?;
+        }
+        if let Some(status) = plan.status {
+            self.set_status(status).await?;
+        }
+        self.add_rules(plan.rules).await?;
+        match plan.enabled {
+            Some(2) => self.lock_configuration().await,
+            Some(enabled) => self.set_enabled(enabled != 0).await,
+            None => Ok(()),
+        }
+    }
+
     /// Enable receiving events in this process.
     ///
     /// This function enable events and set the PID in a single message.
@@ -134,6 +844,10 @@ impl Handle {
     /// disable and enable events, you will want to call the
     /// `Handle::set_enabled` and `Handle::set_pid` directly, so as to
     /// handle the errors in a more granular manner.
+    ///
+    /// If another process, such as auditd, is registered, the kernel
+    /// replies with `EEXIST`, returned as [`Error::NetlinkError`]. Use
+    /// `Handle::register_self` to get the pid of that process instead.
     pub async fn enable_events(&mut self) -> Result<(), Error> {
         let mut status = StatusMessage::new();
         status.enabled = 1;
@@ -144,6 +858,45 @@ impl Handle {
         self.acked_request(req).await
     }
 
+    /// Disable events and unregister this process, reversing
+    /// `Handle::enable_events`, in a single message.
+    ///
+    /// After this call, the kernel stops sending events to this process: the
+    /// `messages` stream returned by `new_connection` will not receive any
+    /// event anymore.
+    pub async fn disable_events(&mut self) -> Result<(), Error> {
+        let mut status = StatusMessage::new();
+        status.enabled = 0;
+        status.pid = 0;
+        status.mask = AUDIT_STATUS_ENABLED | AUDIT_STATUS_PID;
+        let mut req = NetlinkMessage::from(AuditMessage::SetStatus(status));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
+    /// Set the fields of `status` selected by its mask. See
+    /// [`StatusMessageBuilder`](crate::StatusMessageBuilder) to build it.
+    pub async fn set_status(
+        &mut self,
+        status: StatusMessage,
+    ) -> Result<(), Error> {
+        let mut req = NetlinkMessage::from(AuditMessage::SetStatus(status));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
+    /// Enable auditing and lock the configuration, like `auditctl -e 2`.
+    ///
+    /// **This cannot be undone until the next reboot**: the kernel then
+    /// rejects any change of the rules or of the status with `EPERM`,
+    /// including disabling auditing, setting the audit daemon PID and
+    /// unlocking. It is typically the last step of loading the rules at
+    /// boot, to prevent tampering. Avoid it on development machines.
+    pub async fn lock_configuration(&mut self) -> Result<(), Error> {
+        self.set_status(StatusMessageBuilder::new().locked().build())
+            .await
+    }
+
     /// Set whether to enable the audit daemon or not.
     ///
     /// When enabling the audit daemon with this function, you should ensure
@@ -161,12 +914,29 @@ impl Handle {
         self.acked_request(req).await
     }
 
+    /// Set what the kernel should do when it fails to record an audit
+    /// message.
+    pub async fn set_failure(
+        &mut self,
+        mode: FailureMode,
+    ) -> Result<(), Error> {
+        let mut status = StatusMessage::new();
+        status.failure = mode.into();
+        status.mask = AUDIT_STATUS_FAILURE;
+        let mut req = NetlinkMessage::from(AuditMessage::SetStatus(status));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
     /// Set the PID to which audit messages should be addressed.
     ///
     /// You probably want to use either:
     /// - Your own pid, to receive audit events
     /// - 0, to unset the PID restriction, for example when disabling the audit
     ///   connection.
+    ///
+    /// The kernel replies with `EEXIST`, returned as
+    /// [`Error::NetlinkError`], if another process is still registered.
     pub async fn set_pid(&mut self, pid: u32) -> Result<(), Error> {
         let mut status = StatusMessage::new();
         status.pid = pid;
@@ -176,24 +946,322 @@ impl Handle {
         self.acked_request(req).await
     }
 
+    /// Register this process to receive the events, like `Handle::set_pid`
+    /// with the pid of this process. The returned guard unregisters the
+    /// process when it is dropped, and holds the pid that was registered
+    /// before, 0 if there was none.
+    ///
+    /// Return [`Error::AuditDisabled`] without changing anything if
+    /// auditing is disabled, since no event would be received. The kernel
+    /// refuses to replace a process that is still registered, such as
+    /// auditd: this fails with [`Error::PidRegistered`] with its pid. If
+    /// this process is already registered, nothing is sent.
+    ///
+    /// ```no_run
+    /// # async fn run(mut handle: audit::Handle) -> Result<(), audit::Error> {
+    /// let registration = handle.register_self().await?;
+    /// let mut events = handle.events()?;
+    /// // ... read the events until shutting down
+    /// registration.unregister()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn register_self(&mut self) -> Result<PidRegistration, Error> {
+        let status = self.get_status().await?;
+        if !status.is_enabled() {
+            return Err(Error::AuditDisabled);
+        }
+        // The kernel replies with EEXIST even if this process is the one
+        // that is registered
+        if status.pid == process::id() {
+            return Ok(PidRegistration::new(status.pid));
+        }
+        match self.set_pid(process::id()).await {
+            Ok(()) => Ok(PidRegistration::new(status.pid)),
+            Err(e) if e.errno() == Some(libc::EEXIST) => {
+                Err(Error::PidRegistered(status.pid))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Set the maximum number of audit messages per second the kernel is
+    /// allowed to emit. Messages above that rate are dropped and accounted as
+    /// lost.
+    ///
+    /// A value of 0 means there is no rate limit.
+    pub async fn set_rate_limit(
+        &mut self,
+        messages_per_second: u32,
+    ) -> Result<(), Error> {
+        let mut status = StatusMessage::new();
+        status.rate_limiting = messages_per_second;
+        status.mask = AUDIT_STATUS_RATE_LIMIT;
+        let mut req = NetlinkMessage::from(AuditMessage::SetStatus(status));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
+    /// Set the maximum number of audit messages waiting in the kernel queue.
+    /// Once this limit is reached, new messages are handled according to the
+    /// failure mode.
+    pub async fn set_backlog_limit(&mut self, limit: u32) -> Result<(), Error> {
+        let mut status = StatusMessage::new();
+        status.backlog_limit = limit;
+        status.mask = AUDIT_STATUS_BACKLOG_LIMIT;
+        let mut req = NetlinkMessage::from(AuditMessage::SetStatus(status));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
+    /// Set how long the kernel waits for the backlog queue to drain when the
+    /// backlog limit is reached, before dropping the message.
+    ///
+    /// The `wait_time` is expressed in jiffies, i.e. kernel timer ticks, so
+    /// its duration depends on the `CONFIG_HZ` of the running kernel.
+    pub async fn set_backlog_wait_time(
+        &mut self,
+        wait_time: u32,
+    ) -> Result<(), Error> {
+        let mut status = StatusMessage::new();
+        status.backlog_wait_time = wait_time;
+        status.mask = AUDIT_STATUS_BACKLOG_WAIT_TIME;
+        let mut req = NetlinkMessage::from(AuditMessage::SetStatus(status));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
+    /// Reset the kernel counter of lost messages, and return its value before
+    /// the reset.
+    ///
+    /// This requires a kernel supporting the
+    /// `AUDIT_FEATURE_BITMAP_LOST_RESET` feature.
+    pub async fn reset_lost(&mut self) -> Result<u32, Error> {
+        let mut status = StatusMessage::new();
+        status.mask = AUDIT_STATUS_LOST;
+        let mut req = NetlinkMessage::from(AuditMessage::SetStatus(status));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        let mut response = self.timed_request(req)?;
+
+        // The kernel reports the previous counter value as a positive
+        // "error" code in the ACK.
+        match response
+            .next()
+            .await
+            .transpose()?
+            .map(|msg| msg.into_parts())
+        {
+            None => Ok(0),
+            Some((_, NetlinkPayload::Error(err_msg))) => match err_msg.code {
+                Some(code) if code.get() > 0 => Ok(code.get() as u32),
+                _ => Err(Error::from(err_msg)),
+            },
+            Some((header, payload)) => Err(Error::UnexpectedMessage(
+                NetlinkMessage::new(header, payload),
+            )),
+        }
+    }
+
     /// Get current audit status
+    ///
+    /// The request is a plain `NLM_F_REQUEST`, without `NLM_F_DUMP`: the
+    /// kernel replies with a single status message, and some kernels follow
+    /// dump requests with an `NLMSG_DONE` or an ack that used to be mistaken
+    /// for the reply. Whatever the kernel sends after the status is
+    /// consumed, so both behaviors are handled. `Error::RequestFailed` means
+    /// the kernel sent no status at all.
+    ///
+    /// The counters and settings are described in [`AuditMetrics`], which
+    /// `Handle::metrics` returns directly.
+    ///
+    /// ```no_run
+    /// use audit::{new_connection, AuditFeature, StatusMessageExt};
+    ///
+    /// # async fn run() -> Result<(), audit::Error> {
+    /// let (connection, mut handle, _) = new_connection().unwrap();
+    /// tokio::spawn(connection);
+    ///
+    /// let status = handle.get_status().await?;
+    /// println!("enabled: {}", status.is_enabled());
+    /// println!("locked: {}", status.is_locked());
+    /// println!("failure mode: {:?}", status.failure_mode()?);
+    /// if status.supports(AuditFeature::BacklogWaitTime) {
+    ///     println!("backlog wait time: {}", status.backlog_wait_time);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn get_status(&mut self) -> Result<StatusMessage, Error> {
+        // The status is a single message: it does not need to be dumped.
         let mut req = NetlinkMessage::from(AuditMessage::GetStatus(None));
-        req.header.flags = NLM_F_REQUEST | NLM_F_DUMP;
-        let mut request = self.request(req)?;
-
-        let response = request.next().await.ok_or(Error::RequestFailed)?;
-
-        match response.into_parts() {
-            (
-                _,
-                NetlinkPayload::InnerMessage(AuditMessage::GetStatus(Some(
-                    status,
-                ))),
-            ) => Ok(status),
-            (header, payload) => Err(Error::UnexpectedMessage(
-                NetlinkMessage::new(header, payload),
-            )),
+        req.header.flags = NLM_F_REQUEST;
+        let mut response = self.timed_request(req)?;
+
+        // Drain the response, so that a trailing NLMSG_DONE or ACK is
+        // consumed, and keep the status wherever it is.
+        let mut status = None;
+        let mut unexpected = None;
+        while let Some(message) = response.next().await.transpose()? {
+            let (header, payload) = message.into_parts();
+            match payload {
+                NetlinkPayload::InnerMessage(AuditMessage::GetStatus(
+                    Some(reply),
+                )) => {
+                    status.get_or_insert(reply);
+                }
+                NetlinkPayload::Done(_) | NetlinkPayload::Noop => {}
+                NetlinkPayload::Error(err_msg) => {
+                    return Err(Error::from(err_msg))
+                }
+                payload => {
+                    unexpected
+                        .get_or_insert(NetlinkMessage::new(header, payload));
+                }
+            }
+        }
+
+        match (status, unexpected) {
+            (Some(status), _) => Ok(status),
+            (None, Some(message)) => Err(Error::UnexpectedMessage(message)),
+            (None, None) => Err(Error::RequestFailed),
         }
     }
+
+    /// Get the counters and settings of the audit subsystem, from a single
+    /// `Handle::get_status` request
+    pub async fn metrics(&mut self) -> Result<AuditMetrics, Error> {
+        Ok(AuditMetrics::from(&self.get_status().await?))
+    }
+
+    /// Check that the connection and the kernel are responsive, for
+    /// liveness and readiness probes. This sends a `Handle::get_status`
+    /// request and discards the reply, so nothing is changed, and it
+    /// requires the same `CAP_AUDIT_CONTROL` capability.
+    ///
+    /// Like the other requests, this waits forever by default: use a handle
+    /// configured with `Handle::with_timeout` to get an [`Error::Timeout`]
+    /// instead.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// # async fn run(handle: audit::Handle) -> Result<(), audit::Error> {
+    /// let mut probe = handle.clone().with_timeout(Duration::from_secs(1));
+    /// probe.ping().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn ping(&mut self) -> Result<(), Error> {
+        self.get_status().await.map(|_| ())
+    }
+
+    /// Get the version of the audit API, as reported in the status.
+    ///
+    /// The kernel reports it in the same field as
+    /// `StatusMessage::feature_bitmap`: it is a set of `AUDIT_VERSION_*`
+    /// bits rather than a number to compare. For instance,
+    /// `backlog_wait_time` can be set if
+    /// [`AUDIT_VERSION_BACKLOG_WAIT_TIME`] is set. See also
+    /// [`StatusMessageExt::supports`].
+    pub async fn get_version(&mut self) -> Result<u32, Error> {
+        Ok(self.get_status().await?.feature_bitmap)
+    }
+
+    /// Get the pid of the process receiving the audit events, usually
+    /// `auditd`. Return 0 if no process registered, in which case the
+    /// kernel logs the events to the kernel log.
+    pub async fn get_pid(&mut self) -> Result<u32, Error> {
+        Ok(self.get_status().await?.pid)
+    }
+
+    /// Get the kernel audit features
+    pub async fn get_features(&mut self) -> Result<Features, Error> {
+        self.raw_request(AUDIT_GET_FEATURE, String::new(), Features::from_bytes)
+            .await
+    }
+
+    /// Get the uid, pid and security context of the last process that sent a
+    /// signal to the audit daemon (typically to stop or reconfigure it).
+    ///
+    /// The reply is binary, while the codec of the connection only decodes
+    /// valid UTF-8 payloads: the request is thus sent on a short-lived
+    /// socket of the runtime instead, whose reply is decoded as is. It
+    /// follows the timeout of the handle, if any.
+    #[cfg(any(feature = "tokio_socket", feature = "smol_socket"))]
+    pub async fn signal_info(&mut self) -> Result<crate::SignalInfo, Error> {
+        crate::signal_info::request_signal_info(self.timeout).await
+    }
+
+    /// Get the TTY input auditing status of the current process
+    pub async fn get_tty(&mut self) -> Result<TtyStatus, Error> {
+        self.raw_request(AUDIT_TTY_GET, String::new(), TtyStatus::from_bytes)
+            .await
+    }
+
+    /// Set the TTY input auditing status of the current process. It is
+    /// inherited by the processes it spawns.
+    pub async fn set_tty(&mut self, status: TtyStatus) -> Result<(), Error> {
+        let data = String::from_utf8(status.to_bytes())
+            .map_err(|_| Error::UnsupportedPayload(AUDIT_TTY_SET))?;
+        let mut req =
+            NetlinkMessage::from(AuditMessage::Other((AUDIT_TTY_SET, data)));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
+    /// Log a user space message, like `audit_log_user_message` does.
+    /// `msg_type` must be in the `AUDIT_FIRST_USER_MSG..=AUDIT_LAST_USER_MSG`
+    /// or `AUDIT_FIRST_USER_MSG2..=AUDIT_LAST_USER_MSG2` range, for instance
+    /// `AUDIT_USER`.
+    pub async fn send_user_message(
+        &mut self,
+        msg_type: u16,
+        text: &str,
+    ) -> Result<(), Error> {
+        self.send_user_record(UserRecord::new(msg_type, text.into())?)
+            .await
+    }
+
+    /// Log a user space record, such as an `AUDIT_USER_AVC` record. See
+    /// [`UserRecord`] for the supported payloads.
+    pub async fn send_user_record(
+        &mut self,
+        record: UserRecord,
+    ) -> Result<(), Error> {
+        let mut req = NetlinkMessage::from(AuditMessage::try_from(record)?);
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
+
+    /// Enable or disable the given kernel audit feature (for instance
+    /// `AUDIT_FEATURE_LOGINUID_IMMUTABLE`). If `lock` is `true`, the feature
+    /// cannot be changed anymore until the next reboot.
+    pub async fn set_feature(
+        &mut self,
+        feature: u32,
+        enable: bool,
+        lock: bool,
+    ) -> Result<(), Error> {
+        if feature > AUDIT_LAST_FEATURE {
+            return Err(Error::InvalidFeature(feature));
+        }
+        let mut features = Features::new();
+        features.mask = feature_to_mask(feature);
+        if enable {
+            features.features = features.mask;
+        }
+        if lock {
+            features.lock = features.mask;
+        }
+        // netlink-packet-audit has no dedicated message for features, so the
+        // structure is carried as a raw payload.
+        let data = String::from_utf8(features.to_bytes())
+            .map_err(|_| Error::InvalidFeature(feature))?;
+        let mut req = NetlinkMessage::from(AuditMessage::Other((
+            AUDIT_SET_FEATURE,
+            data,
+        )));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        self.acked_request(req).await
+    }
 }
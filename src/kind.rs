@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: MIT
+
+use crate::packet::{constants::*, AuditMessage};
+
+/// Broad category of an audit message, derived from its netlink message
+/// type
+#[derive(Copy, Debug, PartialEq, Eq, Clone, Hash)]
+pub enum AuditMessageKind {
+    /// Kernel configuration commands and replies (`AUDIT_GET`,
+    /// `AUDIT_SET`, `AUDIT_GET_FEATURE`, ...), types 1000 to 1099
+    Config,
+    /// Rule management commands and replies (`AUDIT_ADD_RULE`,
+    /// `AUDIT_LIST_RULES`, ...)
+    Rule,
+    /// Records generated by the kernel (`AUDIT_SYSCALL`, `AUDIT_PATH`,
+    /// `AUDIT_AVC`, ...), types 1300 to 2099
+    Event,
+    /// Messages sent by user space programs, types 1100 to 1199 and 2100
+    /// to 2999
+    User,
+    /// Messages of the audit daemon (`AUDIT_DAEMON_START`, ...), types 1200
+    /// to 1299
+    Daemon,
+    /// Any other message type
+    Other,
+}
+
+impl AuditMessageKind {
+    pub fn from_message_type(message_type: u16) -> Self {
+        use self::AuditMessageKind::*;
+        match message_type {
+            AUDIT_LIST | AUDIT_ADD | AUDIT_DEL | AUDIT_WATCH_INS
+            | AUDIT_WATCH_REM | AUDIT_WATCH_LIST | AUDIT_ADD_RULE
+            | AUDIT_DEL_RULE | AUDIT_LIST_RULES => Rule,
+            AUDIT_USER => User,
+            AUDIT_LOGIN => Event,
+            AUDIT_GET..=1099 => Config,
+            AUDIT_FIRST_USER_MSG..=AUDIT_LAST_USER_MSG => User,
+            AUDIT_DAEMON_START..=1299 => Daemon,
+            1300..=2099 => Event,
+            AUDIT_FIRST_USER_MSG2..=AUDIT_LAST_USER_MSG2 => User,
+            _ => Other,
+        }
+    }
+}
+
+/// Extension methods for [`AuditMessage`]. The netlink message type itself
+/// is given by `AuditMessage::message_type`.
+pub trait AuditMessageExt {
+    fn kind(&self) -> AuditMessageKind;
+
+    /// Return the text of an audit record, such as
+    /// `audit(1700000000.123:42): pid=1 ...`, as received from the kernel.
+    /// This is useful for the record types that
+    /// [`AuditEvent`](crate::AuditEvent) does not parse yet, or to forward
+    /// records verbatim. Return `None` for the messages that are not
+    /// records, such as rule and status replies.
+    fn record_text(&self) -> Option<&str>;
+}
+
+impl AuditMessageExt for AuditMessage {
+    fn kind(&self) -> AuditMessageKind {
+        AuditMessageKind::from_message_type(self.message_type())
+    }
+
+    fn record_text(&self) -> Option<&str> {
+        match self {
+            AuditMessage::Event((message_type, text))
+            | AuditMessage::Other((message_type, text))
+                if *message_type >= AUDIT_FIRST_USER_MSG =>
+            {
+                Some(text)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Names of the message types, as printed in `type=` by auditd and accepted
+// by `auditctl -F msgtype=`. Sorted by type.
+const RECORD_TYPE_NAMES: &[(u16, &str)] = &[
+    (1000, "GET"),
+    (1001, "SET"),
+    (1002, "LIST"),
+    (1003, "ADD"),
+    (1004, "DEL"),
+    (1005, "USER"),
+    (1006, "LOGIN"),
+    (1007, "WATCH_INS"),
+    (1008, "WATCH_REM"),
+    (1009, "WATCH_LIST"),
+    (1010, "SIGNAL_INFO"),
+    (1011, "ADD_RULE"),
+    (1012, "DEL_RULE"),
+    (1013, "LIST_RULES"),
+    (1014, "TRIM"),
+    (1015, "MAKE_EQUIV"),
+    (1016, "TTY_GET"),
+    (1017, "TTY_SET"),
+    (1018, "SET_FEATURE"),
+    (1019, "GET_FEATURE"),
+    (1100, "USER_AUTH"),
+    (1101, "USER_ACCT"),
+    (1102, "USER_MGMT"),
+    (1103, "CRED_ACQ"),
+    (1104, "CRED_DISP"),
+    (1105, "USER_START"),
+    (1106, "USER_END"),
+    (1107, "USER_AVC"),
+    (1108, "USER_CHAUTHTOK"),
+    (1109, "USER_ERR"),
+    (1110, "CRED_REFR"),
+    (1111, "USYS_CONFIG"),
+    (1112, "USER_LOGIN"),
+    (1113, "USER_LOGOUT"),
+    (1114, "ADD_USER"),
+    (1115, "DEL_USER"),
+    (1116, "ADD_GROUP"),
+    (1117, "DEL_GROUP"),
+    (1118, "DAC_CHECK"),
+    (1119, "CHGRP_ID"),
+    (1120, "TEST"),
+    (1121, "TRUSTED_APP"),
+    (1122, "USER_SELINUX_ERR"),
+    (1123, "USER_CMD"),
+    (1124, "USER_TTY"),
+    (1125, "CHUSER_ID"),
+    (1126, "GRP_AUTH"),
+    (1127, "SYSTEM_BOOT"),
+    (1128, "SYSTEM_SHUTDOWN"),
+    (1129, "SYSTEM_RUNLEVEL"),
+    (1130, "SERVICE_START"),
+    (1131, "SERVICE_STOP"),
+    (1200, "DAEMON_START"),
+    (1201, "DAEMON_END"),
+    (1202, "DAEMON_ABORT"),
+    (1203, "DAEMON_CONFIG"),
+    (1300, "SYSCALL"),
+    (1302, "PATH"),
+    (1303, "IPC"),
+    (1304, "SOCKETCALL"),
+    (1305, "CONFIG_CHANGE"),
+    (1306, "SOCKADDR"),
+    (1307, "CWD"),
+    (1309, "EXECVE"),
+    (1311, "IPC_SET_PERM"),
+    (1312, "MQ_OPEN"),
+    (1313, "MQ_SENDRECV"),
+    (1314, "MQ_NOTIFY"),
+    (1315, "MQ_GETSETATTR"),
+    (1316, "KERNEL_OTHER"),
+    (1317, "FD_PAIR"),
+    (1318, "OBJ_PID"),
+    (1319, "TTY"),
+    (1320, "EOE"),
+    (1321, "BPRM_FCAPS"),
+    (1322, "CAPSET"),
+    (1323, "MMAP"),
+    (1324, "NETFILTER_PKT"),
+    (1325, "NETFILTER_CFG"),
+    (1326, "SECCOMP"),
+    (1327, "PROCTITLE"),
+    (1328, "FEATURE_CHANGE"),
+    (1329, "REPLACE"),
+    (1330, "KERN_MODULE"),
+    (1331, "FANOTIFY"),
+    (1400, "AVC"),
+    (1401, "SELINUX_ERR"),
+    (1402, "AVC_PATH"),
+    (1403, "MAC_POLICY_LOAD"),
+    (1404, "MAC_STATUS"),
+    (1405, "MAC_CONFIG_CHANGE"),
+    (1406, "MAC_UNLBL_ALLOW"),
+    (1407, "MAC_CIPSOV4_ADD"),
+    (1408, "MAC_CIPSOV4_DEL"),
+    (1409, "MAC_MAP_ADD"),
+    (1410, "MAC_MAP_DEL"),
+    (1411, "MAC_IPSEC_ADDSA"),
+    (1412, "MAC_IPSEC_DELSA"),
+    (1413, "MAC_IPSEC_ADDSPD"),
+    (1414, "MAC_IPSEC_DELSPD"),
+    (1415, "MAC_IPSEC_EVENT"),
+    (1416, "MAC_UNLBL_STCADD"),
+    (1417, "MAC_UNLBL_STCDEL"),
+    (1418, "MAC_CALIPSO_ADD"),
+    (1419, "MAC_CALIPSO_DEL"),
+    (1700, "ANOM_PROMISCUOUS"),
+    (1701, "ANOM_ABEND"),
+    (1702, "ANOM_LINK"),
+    (1800, "INTEGRITY_DATA"),
+    (1801, "INTEGRITY_METADATA"),
+    (1802, "INTEGRITY_STATUS"),
+    (1803, "INTEGRITY_HASH"),
+    (1804, "INTEGRITY_PCR"),
+    (1805, "INTEGRITY_RULE"),
+    (2000, "KERNEL"),
+];
+
+/// Return the name of a message type, such as `CWD` for `AUDIT_CWD`, as
+/// auditd prints it in the `type=` of log lines
+pub fn record_type_name(message_type: u16) -> Option<&'static str> {
+    RECORD_TYPE_NAMES
+        .binary_search_by_key(&message_type, |(t, _)| *t)
+        .ok()
+        .map(|i| RECORD_TYPE_NAMES[i].1)
+}
+
+/// Return the message type with the given name, such as `AUDIT_CWD` for
+/// `CWD`. Like auditctl, the name is not case sensitive.
+///
+/// ```
+/// use audit::{packet::constants::AUDIT_CONFIG_CHANGE, record_type_from_name};
+///
+/// assert_eq!(
+///     record_type_from_name("CONFIG_CHANGE"),
+///     Some(AUDIT_CONFIG_CHANGE),
+/// );
+/// assert_eq!(record_type_from_name("NOT_A_TYPE"), None);
+/// ```
+pub fn record_type_from_name(name: &str) -> Option<u16> {
+    RECORD_TYPE_NAMES
+        .iter()
+        .find(|(_, n)| n.eq_ignore_ascii_case(name))
+        .map(|(t, _)| *t)
+}
@@ -3,28 +3,102 @@
 mod handle;
 pub use crate::handle::*;
 
+mod connection;
+pub use crate::connection::*;
+
+#[cfg(feature = "tokio_socket")]
+mod client;
+#[cfg(feature = "tokio_socket")]
+pub use crate::client::*;
+
 mod errors;
 pub use crate::errors::*;
 
+mod caps;
+pub use crate::caps::*;
+
+mod registration;
+pub use crate::registration::*;
+
+mod status;
+pub use crate::status::*;
+
+mod features;
+pub use crate::features::*;
+
+mod signal_info;
+pub use crate::signal_info::*;
+
+mod kind;
+pub use crate::kind::*;
+
+mod tty;
+pub use crate::tty::*;
+
+mod user;
+pub use crate::user::*;
+
+mod event;
+pub use crate::event::*;
+
+mod rules;
+pub use crate::rules::*;
+
+mod reconnect;
+pub use crate::reconnect::*;
+
+mod arch;
+pub use crate::arch::*;
+
+mod codec;
+pub use crate::codec::*;
+
+pub mod syscalls;
+
+mod time;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
 pub use netlink_packet_audit as packet;
-use netlink_packet_core::NetlinkMessage;
+pub use netlink_packet_core::{NetlinkMessage, NetlinkPayload};
 pub mod proto {
     pub use netlink_proto::{Connection, ConnectionHandle, Error};
 }
-pub use netlink_proto::sys;
+pub use netlink_proto::sys::{self, SocketAddr};
 
 use std::io;
 
 use futures::channel::mpsc::UnboundedReceiver;
 
+/// Open a netlink audit socket, and return the connection driving it, a
+/// [`Handle`] to send requests, and the receiver of the messages that are
+/// not replies, such as audit events.
+///
+/// # Namespaces
+///
+/// The socket belongs to the network namespace of the calling thread. To
+/// use the socket of another namespace, open the connection from a thread
+/// that joined it with `setns(2)`. `setns` only changes the calling
+/// thread, so do it on a dedicated thread before its runtime opens the
+/// connection.
+///
+/// The audit configuration is not namespaced, though: the rules, the
+/// status and the registered process are shared by the whole system. The
+/// kernel refuses the requests of processes outside of the initial user
+/// namespace with `ECONNREFUSED`, and the configuration requests of
+/// processes outside of the initial pid namespace with `EPERM`
+/// ([`Error::PermissionDenied`]). A container can thus not have its own
+/// audit rules: the container manager configures them from the host, for
+/// instance with [`RuleField::Loginuid`](packet::rules::RuleField::Loginuid)
+/// or key based rules, and filters the events.
 #[allow(clippy::type_complexity)]
 #[cfg(feature = "tokio_socket")]
 pub fn new_connection() -> io::Result<(
-    proto::Connection<
-        packet::AuditMessage,
-        sys::TokioSocket,
-        packet::NetlinkAuditCodec,
-    >,
+    proto::Connection<packet::AuditMessage, sys::TokioSocket, AuditCodec>,
     Handle,
     UnboundedReceiver<(NetlinkMessage<packet::AuditMessage>, sys::SocketAddr)>,
 )> {
@@ -33,7 +107,7 @@ pub fn new_connection() -> io::Result<(
 
 #[allow(clippy::type_complexity)]
 pub fn new_connection_with_socket<S>() -> io::Result<(
-    proto::Connection<packet::AuditMessage, S, packet::NetlinkAuditCodec>,
+    proto::Connection<packet::AuditMessage, S, AuditCodec>,
     Handle,
     UnboundedReceiver<(NetlinkMessage<packet::AuditMessage>, sys::SocketAddr)>,
 )>
@@ -45,3 +119,33 @@ where
     )?;
     Ok((conn, Handle::new(handle), messages))
 }
+
+/// Like [`new_connection`], but also subscribe to the `AUDIT_NLGRP_READLOG`
+/// multicast group.
+///
+/// Unlike registering a PID with `Handle::enable_events`, any number of
+/// processes can receive the audit events this way, for instance alongside
+/// auditd. This requires the `CAP_AUDIT_READ` capability.
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "tokio_socket")]
+pub fn new_connection_multicast() -> io::Result<(
+    proto::Connection<packet::AuditMessage, sys::TokioSocket, AuditCodec>,
+    Handle,
+    UnboundedReceiver<(NetlinkMessage<packet::AuditMessage>, sys::SocketAddr)>,
+)> {
+    new_connection_multicast_with_socket()
+}
+
+/// Like [`new_connection_with_socket`], but also subscribe to the
+/// `AUDIT_NLGRP_READLOG` multicast group. See [`new_connection_multicast`].
+#[allow(clippy::type_complexity)]
+pub fn new_connection_multicast_with_socket<S>() -> io::Result<(
+    proto::Connection<packet::AuditMessage, S, AuditCodec>,
+    Handle,
+    UnboundedReceiver<(NetlinkMessage<packet::AuditMessage>, sys::SocketAddr)>,
+)>
+where
+    S: sys::AsyncSocket,
+{
+    ConnectionBuilder::new().multicast(true).build_with_socket()
+}
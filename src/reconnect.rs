@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: MIT
+
+use std::{future::Future, io, time::Duration};
+
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    future::{self, Either},
+    stream::StreamExt,
+};
+
+use crate::{
+    packet::{rules::RuleMessage, AuditMessage},
+    proto, sys,
+    time::sleep,
+    AuditCodec, ConnectionBuilder, Handle, NetlinkMessage,
+};
+
+/// How long [`ReconnectingConnection`] waits before rebuilding a failed
+/// connection by default.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+type Message = (NetlinkMessage<AuditMessage>, sys::SocketAddr);
+type Connection<S> = proto::Connection<AuditMessage, S, AuditCodec>;
+
+/// Builder for a connection that rebuilds its socket when it fails, for
+/// long-running processes.
+///
+/// The returned connection future drives the netlink connection like the
+/// one of [`new_connection`](crate::new_connection), but when the socket
+/// fails, it opens a new one with the same [`ConnectionBuilder`] settings
+/// after [`ReconnectingConnection::retry_delay`]. The returned [`Handle`]
+/// and its clones then send their requests to the new connection, and the
+/// unsolicited messages of every connection are forwarded to the returned
+/// receiver. Requests in flight when the socket fails are lost.
+///
+/// Each time a connection is opened, the first one included, the events are
+/// enabled for this process if [`ReconnectingConnection::enable_events`] was
+/// called, and the [`ReconnectingConnection::rules`] are added. Rules that
+/// already exist are skipped. Errors are ignored: the connection keeps
+/// running. The future completes once the receiver is dropped and the
+/// current connection fails.
+///
+/// ```no_run
+/// use audit::{ConnectionBuilder, ReconnectingConnection};
+///
+/// # fn run() -> std::io::Result<()> {
+/// let (connection, handle, messages) =
+///     ReconnectingConnection::new(ConnectionBuilder::new())
+///         .enable_events(true)
+///         .build()?;
+/// tokio::spawn(connection);
+/// let handle = handle.with_messages(messages);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReconnectingConnection {
+    builder: ConnectionBuilder,
+    retry_delay: Duration,
+    enable_events: bool,
+    rules: Vec<RuleMessage>,
+}
+
+impl ReconnectingConnection {
+    pub fn new(builder: ConnectionBuilder) -> Self {
+        ReconnectingConnection {
+            builder,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            enable_events: false,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Set how long to wait before opening a new socket. The timer relies
+    /// on the runtime selected by the `tokio_socket` or `smol_socket`
+    /// feature: without any of them, failed connections are not rebuilt.
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Register this process to receive the events on each connection, see
+    /// `Handle::enable_events`
+    pub fn enable_events(mut self, enable: bool) -> Self {
+        self.enable_events = enable;
+        self
+    }
+
+    /// Set the rules to add on each connection
+    pub fn rules(mut self, rules: Vec<RuleMessage>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    #[allow(clippy::type_complexity)]
+    #[cfg(feature = "tokio_socket")]
+    pub fn build(
+        self,
+    ) -> io::Result<(
+        impl Future<Output = ()>,
+        Handle,
+        UnboundedReceiver<Message>,
+    )> {
+        self.build_with_socket::<sys::TokioSocket>()
+    }
+
+    /// Build the first connection. Failing to open it is an error, while the
+    /// following connections are retried until they succeed.
+    #[allow(clippy::type_complexity)]
+    pub fn build_with_socket<S>(
+        self,
+    ) -> io::Result<(
+        impl Future<Output = ()>,
+        Handle,
+        UnboundedReceiver<Message>,
+    )>
+    where
+        S: sys::AsyncSocket,
+    {
+        let (conn, handle, messages) =
+            self.builder.clone().build_with_socket::<S>()?;
+        let (tx, rx) = unbounded();
+        let connection = self.run(conn, handle.clone(), messages, tx);
+        Ok((connection, handle, rx))
+    }
+
+    async fn run<S>(
+        self,
+        conn: Connection<S>,
+        handle: Handle,
+        messages: UnboundedReceiver<Message>,
+        tx: UnboundedSender<Message>,
+    ) where
+        S: sys::AsyncSocket,
+    {
+        let mut next = Some((conn, messages));
+        loop {
+            let (conn, messages) = match next.take() {
+                Some(next) => next,
+                None => match self.builder.clone().build_with_socket::<S>() {
+                    Ok((conn, new_handle, messages)) => {
+                        handle.set_connection(new_handle);
+                        (conn, messages)
+                    }
+                    Err(_) => {
+                        sleep(self.retry_delay).await;
+                        continue;
+                    }
+                },
+            };
+
+            let forward = messages.map(Ok).forward(tx.clone());
+            let session = future::join(self.setup(handle.clone()), forward);
+            match future::select(Box::pin(conn), Box::pin(session)).await {
+                // The socket failed. Now that the connection is dropped, the
+                // pending requests fail and the remaining messages are
+                // forwarded.
+                Either::Left(((), session)) => {
+                    let _ = session.await;
+                }
+                Either::Right((_, conn)) => conn.await,
+            }
+
+            if tx.is_closed() {
+                return;
+            }
+            sleep(self.retry_delay).await;
+        }
+    }
+
+    async fn setup(&self, mut handle: Handle) {
+        if self.enable_events {
+            let _ = handle.enable_events().await;
+        }
+        for rule in self.rules.iter() {
+            // Rules are kept by the kernel when a socket fails, so they
+            // usually already exist
+            let _ = handle.add_rule(rule.clone()).await;
+        }
+    }
+}
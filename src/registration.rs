@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MIT
+
+use std::io;
+
+use netlink_packet_core::{
+    NetlinkMessage, NetlinkPayload, NLM_F_ACK, NLM_F_REQUEST,
+};
+use netlink_sys::{protocols::NETLINK_AUDIT, Socket, SocketAddr};
+
+use crate::{
+    packet::{AuditMessage, StatusMessage},
+    Error, AUDIT_STATUS_PID,
+};
+
+/// Guard returned by `Handle::register_self`. Dropping it unregisters the
+/// process, so that the kernel stops sending the events to it.
+///
+/// Since `Drop` cannot be async, the request is sent on a short-lived
+/// blocking socket instead of the connection of the `Handle`: it does not
+/// need the runtime to be running. The reply is not awaited, so errors are
+/// ignored. The kernel identifies the registered process by its pid, not
+/// by its socket, so this works from any thread of the process.
+///
+/// Nothing runs if the process is killed or calls `std::process::exit`.
+/// Call [`PidRegistration::unregister`] to get the errors, or
+/// [`PidRegistration::keep`] to stay registered.
+#[must_use = "dropping the registration unregisters the process"]
+#[derive(Debug)]
+pub struct PidRegistration {
+    previous: u32,
+    armed: bool,
+}
+
+impl PidRegistration {
+    pub(crate) fn new(previous: u32) -> Self {
+        PidRegistration {
+            previous,
+            armed: true,
+        }
+    }
+
+    /// Pid of the process that was registered before this one, 0 if there
+    /// was none
+    pub fn previous_pid(&self) -> u32 {
+        self.previous
+    }
+
+    /// Unregister the process, and wait for the kernel to acknowledge it.
+    /// Fails with [`Error::PermissionDenied`] if another process replaced
+    /// this one in the meantime.
+    pub fn unregister(mut self) -> Result<(), Error> {
+        self.armed = false;
+        let socket = Socket::new(NETLINK_AUDIT)?;
+        send_unregister(&socket, NLM_F_REQUEST | NLM_F_ACK)?;
+        loop {
+            let (datagram, _) = socket.recv_from_full()?;
+            let reply = NetlinkMessage::<AuditMessage>::deserialize(&datagram)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if let NetlinkPayload::Error(err_msg) = reply.payload {
+                return match err_msg.code {
+                    None => Ok(()),
+                    Some(_) => Err(Error::from(err_msg)),
+                };
+            }
+        }
+    }
+
+    /// Stay registered after the guard is dropped, and return the pid of
+    /// the process that was registered before
+    pub fn keep(mut self) -> u32 {
+        self.armed = false;
+        self.previous
+    }
+}
+
+impl Drop for PidRegistration {
+    fn drop(&mut self) {
+        if self.armed {
+            let _ = Socket::new(NETLINK_AUDIT)
+                .and_then(|socket| send_unregister(&socket, NLM_F_REQUEST));
+        }
+    }
+}
+
+fn send_unregister(socket: &Socket, flags: u16) -> io::Result<()> {
+    let mut status = StatusMessage::new();
+    status.pid = 0;
+    status.mask = AUDIT_STATUS_PID;
+    let mut req = NetlinkMessage::from(AuditMessage::SetStatus(status));
+    req.header.flags = flags;
+    req.finalize();
+    let mut buf = vec![0; req.buffer_len()];
+    req.serialize(&mut buf);
+    socket.send_to(&buf, &SocketAddr::new(0, 0), 0)?;
+    Ok(())
+}
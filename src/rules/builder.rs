@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: MIT
+
+use thiserror::Error;
+
+use crate::{
+    packet::rules::{
+        RuleAction, RuleField, RuleFieldFlags, RuleFlags, RuleMessage,
+        RuleSyscalls,
+    },
+    rules::{parse_syscall, push_key},
+};
+
+#[derive(Clone, Eq, PartialEq, Debug, Error)]
+pub enum BuildError {
+    #[error("Missing rule action")]
+    MissingAction,
+
+    #[error("Missing rule filter list")]
+    MissingFilter,
+
+    #[error("Unknown syscall {0:?}")]
+    UnknownSyscall(String),
+
+    #[error("Watch rules cannot filter on syscalls")]
+    WatchWithSyscalls,
+}
+
+/// Builder for [`RuleMessage`].
+///
+/// ```no_run
+/// use audit::{
+///     packet::{
+///         constants::AUDIT_ARCH_X86_64, RuleAction, RuleField,
+///         RuleFieldFlags, RuleFlags,
+///     },
+///     RuleBuilder,
+/// };
+///
+/// let rule = RuleBuilder::new()
+///     .filter(RuleFlags::FilterExit)
+///     .action(RuleAction::Always)
+///     .arch(AUDIT_ARCH_X86_64)
+///     .syscall("personality")
+///     .field(RuleField::Uid(1000), RuleFieldFlags::Equal)
+///     .key("bypass")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RuleBuilder {
+    flags: Option<RuleFlags>,
+    action: Option<RuleAction>,
+    arch: Option<u32>,
+    syscalls: Vec<String>,
+    all_syscalls: bool,
+    watch: Option<RuleField>,
+    fields: Vec<(RuleField, RuleFieldFlags)>,
+}
+
+impl RuleBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the filter list of the rule. The auditctl lists map to
+    /// `RuleFlags::FilterExit` (`exit`), `FilterTask` (`task`), `FilterUser`
+    /// (`user`), `FilterType` (`exclude`, `AUDIT_FILTER_EXCLUDE` in the
+    /// kernel headers) and `FilterFs` (`filesystem`). For instance, the
+    /// `exclude` list with the `never` action and a `Msgtype` field drops
+    /// the matching records before they are logged.
+    pub fn filter(mut self, flags: RuleFlags) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    pub fn action(mut self, action: RuleAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    /// Set the architecture of the syscalls. It is always emitted as the
+    /// first field of the rule.
+    pub fn arch(mut self, arch: u32) -> Self {
+        self.arch = Some(arch);
+        self
+    }
+
+    /// Add a syscall to the rule, either by name or by number. Names are
+    /// resolved for the architecture given with [`RuleBuilder::arch`] when
+    /// building the rule.
+    pub fn syscall(mut self, syscall: &str) -> Self {
+        self.syscalls.push(syscall.to_string());
+        self
+    }
+
+    /// Match all the syscalls
+    pub fn all_syscalls(mut self) -> Self {
+        self.all_syscalls = true;
+        self
+    }
+
+    /// Make this rule a watch on the given file (`AUDIT_WATCH`). Watch rules
+    /// always use the `exit` filter list and the `always` action.
+    pub fn watch(mut self, path: &str) -> Self {
+        self.watch = Some(RuleField::Watch(path.to_string()));
+        self
+    }
+
+    /// Make this rule a watch on the given directory and its whole subtree
+    /// (`AUDIT_DIR`), like `auditctl -w` does for directories. A plain
+    /// [`RuleBuilder::watch`] on a directory only matches the directory
+    /// inode itself.
+    pub fn dir(mut self, path: &str) -> Self {
+        self.watch = Some(RuleField::Dir(path.to_string()));
+        self
+    }
+
+    /// Set the permissions triggering a watch (`AUDIT_PERM_*` bits)
+    pub fn perm(self, perm: u32) -> Self {
+        self.field(RuleField::Perm(perm), RuleFieldFlags::Equal)
+    }
+
+    /// Add a field to the rule. `flags` is the comparison operator, as
+    /// written by auditctl: `Equal` (`=`), `NotEqual` (`!=`), `LessThan`
+    /// (`<`), `GreaterThan` (`>`), `LessThanOrEqual` (`<=`),
+    /// `GreaterThanOrEqual` (`>=`), `BitMask` (`&`) and `BitTest` (`&=`).
+    pub fn field(mut self, field: RuleField, flags: RuleFieldFlags) -> Self {
+        self.fields.push((field, flags));
+        self
+    }
+
+    /// Add a key to the rule. Like `auditctl -k`, this can be called several
+    /// times: the keys are joined with
+    /// [`AUDIT_KEY_SEPARATOR`](crate::AUDIT_KEY_SEPARATOR).
+    pub fn key(mut self, key: &str) -> Self {
+        push_key(&mut self.fields, key);
+        self
+    }
+
+    pub fn build(self) -> Result<RuleMessage, BuildError> {
+        let mut rule = RuleMessage::new();
+        let arch = self
+            .arch
+            .map(|arch| (RuleField::Arch(arch), RuleFieldFlags::Equal));
+
+        if let Some(watch) = self.watch {
+            if !self.syscalls.is_empty() || self.all_syscalls {
+                return Err(BuildError::WatchWithSyscalls);
+            }
+            rule.flags = RuleFlags::FilterExit;
+            rule.action = RuleAction::Always;
+            rule.fields.extend(arch);
+            rule.fields.push((watch, RuleFieldFlags::Equal));
+            rule.syscalls = RuleSyscalls::new_maxed();
+        } else {
+            rule.flags = self.flags.ok_or(BuildError::MissingFilter)?;
+            rule.action = self.action.ok_or(BuildError::MissingAction)?;
+            rule.fields.extend(arch);
+            if self.syscalls.is_empty() || self.all_syscalls {
+                rule.syscalls = RuleSyscalls::new_maxed();
+            } else {
+                for name in self.syscalls.iter() {
+                    let syscall =
+                        parse_syscall(self.arch, name).map_err(|_| {
+                            BuildError::UnknownSyscall(name.clone())
+                        })?;
+                    rule.syscalls.set(syscall);
+                }
+            }
+        }
+
+        rule.fields.extend(self.fields);
+        Ok(rule)
+    }
+}
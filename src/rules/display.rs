@@ -0,0 +1,224 @@
+// SPDX-License-Identifier: MIT
+
+use std::{convert::TryFrom, fmt};
+
+use crate::{
+    current_arch,
+    packet::rules::{
+        RuleAction, RuleField, RuleFieldFlags, RuleFlags, RuleMessage,
+    },
+    record_type_name,
+    rules::{
+        action_name, arch_name, field_name, is_all_syscalls, list_name,
+        operator_str, perm_str, without_classes, RuleSyscallsExt,
+        AUDIT_KEY_SEPARATOR,
+    },
+    syscalls,
+};
+
+/// Render a [`RuleMessage`] using the `auditctl` syntax, for instance
+/// `-a always,exit -F arch=x86_64 -S personality -F key=bypass` or
+/// `-w /etc/passwd -p rwxa -k my_key`.
+///
+/// Obtained with [`RuleMessageExt::display`](crate::RuleMessageExt::display).
+pub struct RuleDisplay<'a>(pub(crate) &'a RuleMessage);
+
+impl<'a> RuleDisplay<'a> {
+    fn is_watch(&self) -> bool {
+        let rule = self.0;
+        rule.flags == RuleFlags::FilterExit
+            && rule.action == RuleAction::Always
+            && rule.fields.iter().any(|(field, _)| {
+                matches!(field, RuleField::Watch(_) | RuleField::Dir(_))
+            })
+    }
+
+    fn arch(&self) -> Option<u32> {
+        self.0.fields.iter().find_map(|(field, _)| match field {
+            RuleField::Arch(arch) => Some(*arch),
+            _ => None,
+        })
+    }
+
+    fn fmt_watch(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut sep = |f: &mut fmt::Formatter<'_>| {
+            if first {
+                first = false;
+                Ok(())
+            } else {
+                f.write_str(" ")
+            }
+        };
+        for (field, flags) in self.0.fields.iter() {
+            sep(f)?;
+            match (field, flags) {
+                (RuleField::Watch(path), RuleFieldFlags::Equal)
+                | (RuleField::Dir(path), RuleFieldFlags::Equal) => {
+                    write!(f, "-w {path}")?
+                }
+                (RuleField::Perm(perm), RuleFieldFlags::Equal) => {
+                    write!(f, "-p {}", perm_str(*perm))?
+                }
+                (RuleField::Filterkey(keys), RuleFieldFlags::Equal) => {
+                    for (i, key) in keys.split(AUDIT_KEY_SEPARATOR).enumerate()
+                    {
+                        if i > 0 {
+                            f.write_str(" ")?;
+                        }
+                        write!(f, "-k {key}")?;
+                    }
+                }
+                (field, flags) => fmt_field(f, field, *flags)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn fmt_syscall_rule(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = self.0;
+        match (action_name(rule.action), list_name(rule.flags)) {
+            (Some(action), Some(list)) => write!(f, "-a {action},{list}")?,
+            _ => write!(
+                f,
+                "-a {},{}",
+                u32::from(rule.action),
+                u32::from(rule.flags)
+            )?,
+        }
+
+        // The architecture must come before the syscalls, since syscall
+        // numbers depend on it.
+        for (field, flags) in rule.fields.iter() {
+            if let RuleField::Arch(_) = field {
+                f.write_str(" ")?;
+                fmt_field(f, field, *flags)?;
+            }
+        }
+
+        if matches!(rule.flags, RuleFlags::FilterExit | RuleFlags::FilterEntry)
+        {
+            // Like auditctl, ignore the class bits, which the kernel clears
+            if is_all_syscalls(&rule.syscalls) {
+                f.write_str(" -S all")?;
+            } else {
+                let syscalls = without_classes(&rule.syscalls);
+                let mut syscalls = syscalls.iter().peekable();
+                if syscalls.peek().is_some() {
+                    f.write_str(" -S ")?;
+                    let arch = self.arch();
+                    for (i, syscall) in syscalls.enumerate() {
+                        if i > 0 {
+                            f.write_str(",")?;
+                        }
+                        fmt_syscall(f, arch, syscall)?;
+                    }
+                }
+            }
+        }
+
+        for (field, flags) in rule.fields.iter() {
+            if let RuleField::Arch(_) = field {
+                continue;
+            }
+            f.write_str(" ")?;
+            fmt_field(f, field, *flags)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for RuleDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_watch() {
+            self.fmt_watch(f)
+        } else {
+            self.fmt_syscall_rule(f)
+        }
+    }
+}
+
+/// Write the name of the syscall, or its number if it is unknown. Rules
+/// without an `arch` field apply to the native architecture.
+fn fmt_syscall(
+    f: &mut fmt::Formatter<'_>,
+    arch: Option<u32>,
+    syscall: u32,
+) -> fmt::Result {
+    match syscalls::name(arch.unwrap_or_else(current_arch), syscall) {
+        Some(name) => f.write_str(name),
+        None => write!(f, "{syscall}"),
+    }
+}
+
+fn fmt_field(
+    f: &mut fmt::Formatter<'_>,
+    field: &RuleField,
+    flags: RuleFieldFlags,
+) -> fmt::Result {
+    use crate::packet::rules::RuleField::*;
+
+    if let Filterkey(keys) = field {
+        // One -F key=... per key, as auditctl -l prints them
+        for (i, key) in keys.split(AUDIT_KEY_SEPARATOR).enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "-F key{}{key}", operator_str(flags))?;
+        }
+        return Ok(());
+    }
+
+    write!(f, "-F {}{}", field_name(field), operator_str(flags))?;
+    match field {
+        Uid(id) | Euid(id) | Suid(id) | Fsuid(id) | Gid(id) | Egid(id)
+        | Sgid(id) | Fsgid(id) | Loginuid(id) | ObjUid(id) | ObjGid(id)
+            if *id == u32::MAX =>
+        {
+            f.write_str("unset")
+        }
+        Arch(arch) => match arch_name(*arch) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{arch:#x}"),
+        },
+        Msgtype(message_type) => {
+            match u16::try_from(*message_type).ok().and_then(record_type_name) {
+                Some(name) => f.write_str(name),
+                None => write!(f, "{message_type}"),
+            }
+        }
+        Exit(value) => write!(f, "{}", *value as i32),
+        Perm(perm) => f.write_str(&perm_str(*perm)),
+        Pid(v) | Uid(v) | Euid(v) | Suid(v) | Fsuid(v) | Gid(v) | Egid(v)
+        | Sgid(v) | Fsgid(v) | Loginuid(v) | Pers(v) | Ppid(v)
+        | LoginuidSet(v) | Sessionid(v) | Fstype(v) | Devmajor(v)
+        | Devminor(v) | Inode(v) | Success(v) | Filetype(v) | ObjUid(v)
+        | ObjGid(v) | FieldCompare(v) | Exe(v) | Arg0(v) | Arg1(v)
+        | Arg2(v) | Arg3(v) => write!(f, "{v}"),
+        Watch(s) | Dir(s) | Filterkey(s) | SubjUser(s) | SubjRole(s)
+        | SubjType(s) | SubjSen(s) | SubjClr(s) | ObjUser(s) | ObjRole(s)
+        | ObjType(s) | ObjLevLow(s) | ObjLevHigh(s) => f.write_str(s),
+        _ => f.write_str("?"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_rule, RuleMessageExt};
+
+    #[test]
+    fn display_listed_syscalls() {
+        let line = "-a always,exit -S all -F uid=0";
+        let mut rule = parse_rule(line).unwrap();
+        // The kernel lists rules without the syscall class bits
+        rule.syscalls = without_classes(&rule.syscalls);
+        assert!(!rule.syscalls.is_all());
+        assert_eq!(rule.display().to_string(), line);
+
+        let line = "-a always,exit -F arch=x86_64 -S read,write";
+        let mut rule = parse_rule(line).unwrap();
+        rule.syscalls.set(2047);
+        assert_eq!(rule.display().to_string(), line);
+    }
+}
@@ -0,0 +1,431 @@
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    packet::{
+        constants::{
+            AUDIT_BITMASK_SIZE, AUDIT_FILTER_EXIT, AUDIT_FILTER_FS,
+            AUDIT_FILTER_PREPEND, AUDIT_FILTER_TASK, AUDIT_FILTER_TYPE,
+            AUDIT_FILTER_USER, AUDIT_MAX_FIELDS, AUDIT_MAX_KEY_LEN,
+            AUDIT_PERM_ATTR, AUDIT_PERM_EXEC, AUDIT_PERM_READ,
+            AUDIT_PERM_WRITE,
+        },
+        rules::{
+            RuleAction, RuleBuffer, RuleField, RuleFieldFlags, RuleFlags,
+            RuleMessage, RuleSyscalls, RuleSyscallsIter,
+        },
+    },
+    rules::{NormalizedRule, RuleDisplay},
+    Error,
+};
+use netlink_packet_utils::{Emitable, Parseable};
+
+const AUDIT_PERM_ALL: u32 =
+    AUDIT_PERM_READ | AUDIT_PERM_WRITE | AUDIT_PERM_EXEC | AUDIT_PERM_ATTR;
+
+// Longest path the kernel resolves, without the trailing NUL
+const PATH_MAX: usize = libc::PATH_MAX as usize - 1;
+
+/// Separator of the keys of a rule. The kernel only accepts a single
+/// `RuleField::Filterkey` per rule, so auditctl joins the keys given with
+/// several `-k` options into one field, as `key1\x01key2`.
+pub const AUDIT_KEY_SEPARATOR: char = '\x01';
+
+/// Additional methods for [`RuleMessage`]
+pub trait RuleMessageExt {
+    /// Return an object that renders the rule using the `auditctl` syntax
+    fn display(&self) -> RuleDisplay<'_>;
+
+    /// Return the keys of the rule, in order. See [`AUDIT_KEY_SEPARATOR`].
+    ///
+    /// ```
+    /// use audit::{parse_rule, RuleMessageExt};
+    ///
+    /// let rule = parse_rule("-w /etc/shadow -p wa -k cis-6.1 -k identity")
+    ///     .unwrap();
+    /// assert_eq!(rule.keys(), ["cis-6.1", "identity"]);
+    /// assert_eq!(
+    ///     rule.display().to_string(),
+    ///     "-w /etc/shadow -p wa -k cis-6.1 -k identity",
+    /// );
+    /// ```
+    fn keys(&self) -> Vec<&str>;
+
+    /// Return `true` if both rules are the same, regardless of the order of
+    /// their fields. See [`NormalizedRule`].
+    fn is_equivalent(&self, other: &RuleMessage) -> bool;
+
+    /// Check the constraints the kernel puts on actions, filter lists,
+    /// watch rules, permissions and the size of the rule, and return
+    /// [`Error::InvalidRule`] if the rule breaks one of them.
+    /// `Handle::add_rule` calls this before sending the rule, since the
+    /// kernel only replies with `EINVAL`.
+    fn validate(&self) -> Result<(), Error>;
+
+    /// Encode the rule as `Handle::add_rule` sends it to the kernel (a
+    /// `struct audit_rule_data`, without the netlink header). The buffer is
+    /// sized to the rule, string values included, so rules larger than a
+    /// page are sent whole. Along with
+    /// [`RuleMessageExt::from_netlink_bytes`] and
+    /// [`RuleMessageExt::validate`], this allows checking rules without a
+    /// privileged socket.
+    fn to_netlink_bytes(&self) -> Vec<u8>;
+
+    /// Decode a rule encoded by [`RuleMessageExt::to_netlink_bytes`] or
+    /// received from the kernel. Return [`Error::InvalidRule`] if the bytes
+    /// are not a valid rule.
+    fn from_netlink_bytes(bytes: &[u8]) -> Result<RuleMessage, Error>
+    where
+        Self: Sized;
+}
+
+impl RuleMessageExt for RuleMessage {
+    fn display(&self) -> RuleDisplay<'_> {
+        RuleDisplay(self)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.fields
+            .iter()
+            .filter_map(|(field, _)| match field {
+                RuleField::Filterkey(keys) => Some(keys),
+                _ => None,
+            })
+            .flat_map(|keys| keys.split(AUDIT_KEY_SEPARATOR))
+            .collect()
+    }
+
+    fn is_equivalent(&self, other: &RuleMessage) -> bool {
+        // Cloning is needed since NormalizedRule owns the rule
+        NormalizedRule(self.clone()) == NormalizedRule(other.clone())
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |reason: &str| Err(Error::InvalidRule(reason.into()));
+
+        // The kernel rejects the deprecated `possible` action and `entry`
+        // list
+        if !matches!(self.action, RuleAction::Always | RuleAction::Never) {
+            return invalid("the action must be always or never");
+        }
+        let list = filter_list(self.flags);
+        if ![
+            AUDIT_FILTER_USER,
+            AUDIT_FILTER_TASK,
+            AUDIT_FILTER_EXIT,
+            AUDIT_FILTER_TYPE,
+            AUDIT_FILTER_FS,
+        ]
+        .contains(&list)
+        {
+            return invalid(
+                "the filter list must be exit, task, user, exclude or \
+                 filesystem",
+            );
+        }
+
+        if self.fields.len() > AUDIT_MAX_FIELDS {
+            return invalid("a rule can have at most 64 fields");
+        }
+
+        let keys = self
+            .fields
+            .iter()
+            .filter(|(field, _)| matches!(field, RuleField::Filterkey(_)))
+            .count();
+        if keys > 1 {
+            return invalid(
+                "a rule can only have one key field, join the keys with \
+                 AUDIT_KEY_SEPARATOR",
+            );
+        }
+
+        let mut watches = 0;
+        for (field, flags) in self.fields.iter() {
+            if let Some(len) = string_len(field) {
+                if matches!(field, RuleField::Filterkey(_)) {
+                    if len > AUDIT_MAX_KEY_LEN {
+                        return invalid("keys are limited to 256 bytes");
+                    }
+                } else if len > PATH_MAX {
+                    return invalid("string values are limited to 4095 bytes");
+                }
+            }
+            match field {
+                RuleField::Msgtype(_)
+                    if list != AUDIT_FILTER_TYPE
+                        && list != AUDIT_FILTER_USER =>
+                {
+                    return invalid(
+                        "msgtype requires the exclude or user filter list",
+                    );
+                }
+                RuleField::Fstype(_) if list != AUDIT_FILTER_FS => {
+                    return invalid(
+                        "fstype requires the filesystem filter list",
+                    );
+                }
+                RuleField::Fstype(_) | RuleField::Filterkey(_) => {}
+                _ if list == AUDIT_FILTER_FS => {
+                    return invalid(
+                        "the filesystem filter list only supports the fstype \
+                         and key fields",
+                    );
+                }
+                RuleField::Watch(path) | RuleField::Dir(path) => {
+                    watches += 1;
+                    if *flags != RuleFieldFlags::Equal {
+                        return invalid("watches only support the = operator");
+                    }
+                    if !path.starts_with('/') {
+                        return invalid("watched paths must be absolute");
+                    }
+                    if matches!(field, RuleField::Watch(_))
+                        && path.ends_with('/')
+                    {
+                        return invalid(
+                            "watched files cannot end with a slash, use a \
+                             directory watch instead",
+                        );
+                    }
+                }
+                RuleField::Perm(perm) => {
+                    if perm & !AUDIT_PERM_ALL != 0 {
+                        return invalid("permissions must be within 0..=15");
+                    }
+                    if !is_exit_list(self.flags) {
+                        return invalid(
+                            "permissions require the exit filter list",
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match watches {
+            0 => Ok(()),
+            1 if !is_exit_list(self.flags) => {
+                invalid("watches require the exit filter list")
+            }
+            // Rules listed by the kernel do not have the class bits
+            1 if !is_all_syscalls(&self.syscalls) => {
+                invalid("watches cannot filter on syscalls")
+            }
+            1 => Ok(()),
+            _ => invalid("a rule can only have one watch"),
+        }
+    }
+
+    fn to_netlink_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.buffer_len()];
+        self.emit(&mut bytes);
+        bytes
+    }
+
+    fn from_netlink_bytes(bytes: &[u8]) -> Result<RuleMessage, Error> {
+        RuleBuffer::new_checked(bytes)
+            .and_then(|buf| RuleMessage::parse(&buf))
+            .map_err(|e| Error::InvalidRule(e.to_string()))
+    }
+}
+
+/// Add `key` to the keys of a rule, like auditctl does for each `-k`
+pub(crate) fn push_key(
+    fields: &mut Vec<(RuleField, RuleFieldFlags)>,
+    key: &str,
+) {
+    for (field, _) in fields.iter_mut() {
+        if let RuleField::Filterkey(keys) = field {
+            keys.push(AUDIT_KEY_SEPARATOR);
+            keys.push_str(key);
+            return;
+        }
+    }
+    fields.push((RuleField::Filterkey(key.to_string()), RuleFieldFlags::Equal));
+}
+
+/// Return the filter list of the rule, without the `-A` prepend flag
+fn filter_list(flags: RuleFlags) -> u32 {
+    u32::from(flags) & !AUDIT_FILTER_PREPEND
+}
+
+/// Return `true` for the exit filter list, `-A` rules included
+fn is_exit_list(flags: RuleFlags) -> bool {
+    filter_list(flags) == AUDIT_FILTER_EXIT
+}
+
+/// Return the length of the value of string fields, `None` for numbers
+fn string_len(field: &RuleField) -> Option<usize> {
+    use RuleField::*;
+    match field {
+        Watch(s) | Dir(s) | Filterkey(s) | SubjUser(s) | SubjRole(s)
+        | SubjType(s) | SubjSen(s) | SubjClr(s) | ObjUser(s) | ObjRole(s)
+        | ObjType(s) | ObjLevLow(s) | ObjLevHigh(s) => Some(s.len()),
+        _ => None,
+    }
+}
+
+/// Number of syscalls the bitmap of a rule can hold. `RuleSyscalls::set`
+/// panics for syscalls beyond it.
+pub(crate) const MAX_SYSCALLS: u32 = AUDIT_BITMASK_SIZE as u32 * 32;
+
+/// Number of syscall classes. The last bits of the syscall bitmap select
+/// classes of syscalls rather than syscalls: the kernel adds the syscalls of
+/// these classes to the bitmap when it stores a rule, and clears the bits.
+pub(crate) const AUDIT_SYSCALL_CLASSES: u32 = 16;
+
+/// Return the bitmap without its class bits, as the kernel stores it. See
+/// [`AUDIT_SYSCALL_CLASSES`].
+pub(crate) fn without_classes(syscalls: &RuleSyscalls) -> RuleSyscalls {
+    let mut syscalls = syscalls.clone();
+    for bit in MAX_SYSCALLS - AUDIT_SYSCALL_CLASSES..MAX_SYSCALLS {
+        syscalls.unset(bit);
+    }
+    syscalls
+}
+
+/// Return `true` if all the syscalls are set, regardless of the class bits
+pub(crate) fn is_all_syscalls(syscalls: &RuleSyscalls) -> bool {
+    (0..MAX_SYSCALLS - AUDIT_SYSCALL_CLASSES).all(|bit| syscalls.has(bit))
+}
+
+/// Additional methods for [`RuleSyscalls`]. Setting and unsetting syscalls
+/// is provided by [`RuleSyscalls::set`] and [`RuleSyscalls::unset`].
+pub trait RuleSyscallsExt {
+    /// Build a bitmap with the given syscalls set, and the others unset.
+    /// Return [`Error::InvalidRule`] if a syscall number does not fit in the
+    /// 2048 bits of the bitmap.
+    ///
+    /// ```
+    /// use audit::{packet::rules::RuleSyscalls, RuleSyscallsExt};
+    ///
+    /// let syscalls = RuleSyscalls::from_numbers(&[59, 0, 322, 31]).unwrap();
+    /// assert_eq!(syscalls.to_numbers(), vec![0, 31, 59, 322]);
+    /// assert!(syscalls.contains(31) && !syscalls.contains(32));
+    /// assert!(!syscalls.contains(4096));
+    /// assert!(RuleSyscalls::from_numbers(&[2047]).is_ok());
+    /// assert!(RuleSyscalls::from_numbers(&[2048]).is_err());
+    /// ```
+    fn from_numbers(syscalls: &[u32]) -> Result<RuleSyscalls, Error>
+    where
+        Self: Sized;
+
+    /// Return the numbers of the syscalls that are set, in increasing order
+    fn to_numbers(&self) -> Vec<u32>;
+
+    /// Iterate over the numbers of the syscalls that are set
+    fn iter(&self) -> RuleSyscallsIter<&RuleSyscalls>;
+
+    /// Return `true` if the given syscall is set. Syscalls that do not fit
+    /// in the bitmap are never set.
+    fn contains(&self, syscall: u32) -> bool;
+}
+
+impl RuleSyscallsExt for RuleSyscalls {
+    fn from_numbers(syscalls: &[u32]) -> Result<RuleSyscalls, Error> {
+        let mut bitmap = RuleSyscalls::new_zeroed();
+        for syscall in syscalls {
+            if *syscall >= MAX_SYSCALLS {
+                return Err(Error::InvalidRule(format!(
+                    "syscall {syscall} is beyond the {MAX_SYSCALLS} syscalls \
+                     of the bitmap"
+                )));
+            }
+            bitmap.set(*syscall);
+        }
+        Ok(bitmap)
+    }
+
+    fn to_numbers(&self) -> Vec<u32> {
+        self.iter().collect()
+    }
+
+    fn iter(&self) -> RuleSyscallsIter<&RuleSyscalls> {
+        self.into_iter()
+    }
+
+    fn contains(&self, syscall: u32) -> bool {
+        syscall < MAX_SYSCALLS && self.has(syscall)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::rules::RuleFieldFlags as Flags;
+
+    #[test]
+    fn iter_all_syscalls() {
+        let all = RuleSyscalls::new_maxed();
+        assert_eq!(all.iter().count(), MAX_SYSCALLS as usize);
+        assert_eq!(all.iter().next(), Some(0));
+        assert_eq!(all.iter().last(), Some(MAX_SYSCALLS - 1));
+        assert_eq!(RuleSyscalls::new_zeroed().iter().count(), 0);
+    }
+
+    #[test]
+    fn contains_syscalls() {
+        let mut syscalls = RuleSyscalls::new_zeroed();
+        syscalls.set(59);
+        syscalls.set(2047);
+        assert_eq!(syscalls.iter().collect::<Vec<_>>(), [59, 2047]);
+        assert!(syscalls.contains(59) && syscalls.contains(2047));
+        assert!(!syscalls.contains(58) && !syscalls.contains(2048));
+        assert!(!RuleSyscalls::new_maxed().contains(u32::MAX));
+    }
+
+    #[test]
+    fn validate_listed_watches() {
+        let mut rule = crate::parse_rule("-w /etc/passwd -p wa").unwrap();
+        rule.validate().unwrap();
+        // The kernel lists watches without the syscall class bits
+        rule.syscalls = without_classes(&rule.syscalls);
+        assert!(!rule.syscalls.is_all());
+        rule.validate().unwrap();
+
+        rule.syscalls.unset(0);
+        assert!(matches!(rule.validate(), Err(Error::InvalidRule(_))));
+    }
+
+    /// Build a valid rule whose encoding is `len` bytes long, most of them
+    /// string values
+    fn rule_of_len(len: usize) -> RuleMessage {
+        let mut rule = RuleMessage::new();
+        rule.flags = RuleFlags::FilterExit;
+        rule.action = RuleAction::Always;
+        rule.syscalls = RuleSyscalls::new_maxed();
+        rule.fields = vec![
+            (
+                RuleField::Dir(format!("/{}", "d".repeat(3999))),
+                Flags::Equal,
+            ),
+            (RuleField::ObjUser("u".repeat(1000)), Flags::Equal),
+            (RuleField::ObjRole("r".repeat(1000)), Flags::Equal),
+            (RuleField::SubjType(String::new()), Flags::Equal),
+            (RuleField::Filterkey("k".repeat(256)), Flags::Equal),
+        ];
+        let missing = len - rule.buffer_len();
+        rule.fields[3].0 = RuleField::SubjType("t".repeat(missing));
+        rule
+    }
+
+    #[test]
+    fn large_rules_round_trip() {
+        for len in [8191, 8192, 8193, 9000] {
+            let rule = rule_of_len(len);
+            rule.validate().unwrap();
+            let bytes = rule.to_netlink_bytes();
+            assert_eq!(bytes.len(), len);
+            assert_eq!(RuleMessage::from_netlink_bytes(&bytes).unwrap(), rule);
+        }
+    }
+
+    #[test]
+    fn truncated_rules_are_rejected() {
+        let bytes = rule_of_len(8193).to_netlink_bytes();
+        assert!(matches!(
+            RuleMessage::from_netlink_bytes(&bytes[..8192]),
+            Err(Error::InvalidRule(_))
+        ));
+    }
+}
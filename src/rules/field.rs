@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    packet::{
+        constants::*,
+        rules::{RuleAction, RuleField, RuleFieldFlags, RuleFlags},
+    },
+    AUDIT_ARCH_RISCV64,
+};
+
+/// Name of the field, as used by `auditctl -F <name>=<value>`
+pub(crate) fn field_name(field: &RuleField) -> &'static str {
+    use crate::packet::rules::RuleField::*;
+    match field {
+        Pid(_) => "pid",
+        Uid(_) => "uid",
+        Euid(_) => "euid",
+        Suid(_) => "suid",
+        Fsuid(_) => "fsuid",
+        Gid(_) => "gid",
+        Egid(_) => "egid",
+        Sgid(_) => "sgid",
+        Fsgid(_) => "fsgid",
+        Loginuid(_) => "auid",
+        Pers(_) => "pers",
+        Arch(_) => "arch",
+        Msgtype(_) => "msgtype",
+        Ppid(_) => "ppid",
+        LoginuidSet(_) => "loginuid_set",
+        Sessionid(_) => "sessionid",
+        Fstype(_) => "fstype",
+        Devmajor(_) => "devmajor",
+        Devminor(_) => "devminor",
+        Inode(_) => "inode",
+        Exit(_) => "exit",
+        Success(_) => "success",
+        Perm(_) => "perm",
+        Filetype(_) => "filetype",
+        ObjUid(_) => "obj_uid",
+        ObjGid(_) => "obj_gid",
+        FieldCompare(_) => "field_compare",
+        Exe(_) => "exe",
+        Arg0(_) => "a0",
+        Arg1(_) => "a1",
+        Arg2(_) => "a2",
+        Arg3(_) => "a3",
+        Watch(_) => "path",
+        Dir(_) => "dir",
+        Filterkey(_) => "key",
+        SubjUser(_) => "subj_user",
+        SubjRole(_) => "subj_role",
+        SubjType(_) => "subj_type",
+        SubjSen(_) => "subj_sen",
+        SubjClr(_) => "subj_clr",
+        ObjUser(_) => "obj_user",
+        ObjRole(_) => "obj_role",
+        ObjType(_) => "obj_type",
+        ObjLevLow(_) => "obj_lev_low",
+        ObjLevHigh(_) => "obj_lev_high",
+        _ => "unknown",
+    }
+}
+
+/// Operator of the field, as used by `auditctl -F <name><op><value>`
+pub(crate) fn operator_str(flags: RuleFieldFlags) -> &'static str {
+    use crate::packet::rules::RuleFieldFlags::*;
+    match flags {
+        BitMask => "&",
+        BitTest => "&=",
+        LessThan => "<",
+        GreaterThan => ">",
+        NotEqual => "!=",
+        Equal | None => "=",
+        LessThanOrEqual => "<=",
+        GreaterThanOrEqual => ">=",
+        _ => "?",
+    }
+}
+
+/// Name of the action, as used by `auditctl -a <action>,<list>`
+pub(crate) fn action_name(action: RuleAction) -> Option<&'static str> {
+    match action {
+        RuleAction::Never => Some("never"),
+        RuleAction::Possible => Some("possible"),
+        RuleAction::Always => Some("always"),
+        _ => None,
+    }
+}
+
+/// Name of the filter list, as used by `auditctl -a <action>,<list>`
+pub(crate) fn list_name(flags: RuleFlags) -> Option<&'static str> {
+    match flags {
+        RuleFlags::FilterUser => Some("user"),
+        RuleFlags::FilterTask => Some("task"),
+        RuleFlags::FilterEntry => Some("entry"),
+        RuleFlags::FilterExit => Some("exit"),
+        RuleFlags::FilterType => Some("exclude"),
+        RuleFlags::FilterFs => Some("filesystem"),
+        _ => None,
+    }
+}
+
+/// Name of the architecture, as understood by `auditctl -F arch=<name>`
+pub(crate) fn arch_name(arch: u32) -> Option<&'static str> {
+    let name = match arch {
+        AUDIT_ARCH_X86_64 => "x86_64",
+        AUDIT_ARCH_I386 => "i386",
+        AUDIT_ARCH_AARCH64 => "aarch64",
+        AUDIT_ARCH_ARM => "arm",
+        AUDIT_ARCH_PPC => "ppc",
+        AUDIT_ARCH_PPC64 => "ppc64",
+        AUDIT_ARCH_PPC64LE => "ppc64le",
+        AUDIT_ARCH_S390 => "s390",
+        AUDIT_ARCH_S390X => "s390x",
+        AUDIT_ARCH_IA64 => "ia64",
+        AUDIT_ARCH_RISCV64 => "riscv64",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Render the permissions of a watch as `auditctl -p` does, for instance
+/// `rwxa`
+pub(crate) fn perm_str(perm: u32) -> String {
+    let mut s = String::new();
+    for (bit, c) in [
+        (AUDIT_PERM_READ, 'r'),
+        (AUDIT_PERM_WRITE, 'w'),
+        (AUDIT_PERM_EXEC, 'x'),
+        (AUDIT_PERM_ATTR, 'a'),
+    ] {
+        if perm & bit == bit {
+            s.push(c);
+        }
+    }
+    s
+}
+
+/// Value of a field, as stored in the `values` array or the strings buffer
+/// of a rule
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub(crate) enum RawValue<'a> {
+    Number(u32),
+    Str(&'a str),
+}
+
+/// Return the `AUDIT_*` type of the field and its value
+pub(crate) fn field_type_value(field: &RuleField) -> (u32, RawValue<'_>) {
+    use self::RawValue::*;
+    use crate::packet::rules::RuleField::*;
+    match field {
+        Pid(v) => (AUDIT_PID, Number(*v)),
+        Uid(v) => (AUDIT_UID, Number(*v)),
+        Euid(v) => (AUDIT_EUID, Number(*v)),
+        Suid(v) => (AUDIT_SUID, Number(*v)),
+        Fsuid(v) => (AUDIT_FSUID, Number(*v)),
+        Gid(v) => (AUDIT_GID, Number(*v)),
+        Egid(v) => (AUDIT_EGID, Number(*v)),
+        Sgid(v) => (AUDIT_SGID, Number(*v)),
+        Fsgid(v) => (AUDIT_FSGID, Number(*v)),
+        Loginuid(v) => (AUDIT_LOGINUID, Number(*v)),
+        Pers(v) => (AUDIT_PERS, Number(*v)),
+        Arch(v) => (AUDIT_ARCH, Number(*v)),
+        Msgtype(v) => (AUDIT_MSGTYPE, Number(*v)),
+        Ppid(v) => (AUDIT_PPID, Number(*v)),
+        LoginuidSet(v) => (AUDIT_LOGINUID_SET, Number(*v)),
+        Sessionid(v) => (AUDIT_SESSIONID, Number(*v)),
+        Fstype(v) => (AUDIT_FSTYPE, Number(*v)),
+        Devmajor(v) => (AUDIT_DEVMAJOR, Number(*v)),
+        Devminor(v) => (AUDIT_DEVMINOR, Number(*v)),
+        Inode(v) => (AUDIT_INODE, Number(*v)),
+        Exit(v) => (AUDIT_EXIT, Number(*v)),
+        Success(v) => (AUDIT_SUCCESS, Number(*v)),
+        Perm(v) => (AUDIT_PERM, Number(*v)),
+        Filetype(v) => (AUDIT_FILETYPE, Number(*v)),
+        ObjUid(v) => (AUDIT_OBJ_UID, Number(*v)),
+        ObjGid(v) => (AUDIT_OBJ_GID, Number(*v)),
+        FieldCompare(v) => (AUDIT_FIELD_COMPARE, Number(*v)),
+        Exe(v) => (AUDIT_EXE, Number(*v)),
+        Arg0(v) => (AUDIT_ARG0, Number(*v)),
+        Arg1(v) => (AUDIT_ARG1, Number(*v)),
+        Arg2(v) => (AUDIT_ARG2, Number(*v)),
+        Arg3(v) => (AUDIT_ARG3, Number(*v)),
+        Watch(s) => (AUDIT_WATCH, Str(s)),
+        Dir(s) => (AUDIT_DIR, Str(s)),
+        Filterkey(s) => (AUDIT_FILTERKEY, Str(s)),
+        SubjUser(s) => (AUDIT_SUBJ_USER, Str(s)),
+        SubjRole(s) => (AUDIT_SUBJ_ROLE, Str(s)),
+        SubjType(s) => (AUDIT_SUBJ_TYPE, Str(s)),
+        SubjSen(s) => (AUDIT_SUBJ_SEN, Str(s)),
+        SubjClr(s) => (AUDIT_SUBJ_CLR, Str(s)),
+        ObjUser(s) => (AUDIT_OBJ_USER, Str(s)),
+        ObjRole(s) => (AUDIT_OBJ_ROLE, Str(s)),
+        ObjType(s) => (AUDIT_OBJ_TYPE, Str(s)),
+        ObjLevLow(s) => (AUDIT_OBJ_LEV_LOW, Str(s)),
+        ObjLevHigh(s) => (AUDIT_OBJ_LEV_HIGH, Str(s)),
+        _ => (u32::MAX, Number(0)),
+    }
+}
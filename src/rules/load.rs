@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: MIT
+
+use std::{convert::TryFrom, fs, path::Path};
+
+use crate::{
+    packet::{rules::RuleMessage, StatusMessage},
+    rules::parse_rule,
+    Error, FailureMode, StatusMessageBuilder,
+};
+
+/// What a set of `audit.rules` files asks for, in the order auditctl
+/// applies it. Use `Handle::apply_rules_plan` to apply it.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RulesPlan {
+    /// `-D`: delete all the rules first
+    pub delete_all: bool,
+    /// `-b`, `-f`, `-r` and `--backlog_wait_time` settings, if any
+    pub status: Option<StatusMessage>,
+    pub rules: Vec<RuleMessage>,
+    /// `-e`: 0 to disable auditing, 1 to enable it, 2 to lock the
+    /// configuration. It is applied last, since nothing can be changed once
+    /// the configuration is locked.
+    pub enabled: Option<u32>,
+}
+
+impl RulesPlan {
+    fn status<F>(&mut self, set: F)
+    where
+        F: FnOnce(StatusMessageBuilder) -> StatusMessageBuilder,
+    {
+        let builder = match self.status.take() {
+            Some(status) => StatusMessageBuilder::from(status),
+            None => StatusMessageBuilder::new(),
+        };
+        self.status = Some(set(builder).build());
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let mut tokens = line.split_whitespace();
+        let option = tokens.next().unwrap_or_default();
+        let mut number = || -> Result<u32, String> {
+            let arg = tokens
+                .next()
+                .ok_or_else(|| format!("missing argument for {option}"))?;
+            arg.parse()
+                .map_err(|_| format!("invalid argument {arg:?} for {option}"))
+        };
+        match option {
+            "-D" => self.delete_all = true,
+            "-b" => {
+                let limit = number()?;
+                self.status(|status| status.backlog_limit(limit));
+            }
+            "-f" => {
+                let mode = FailureMode::try_from(number()?)
+                    .map_err(|e| e.to_string())?;
+                self.status(|status| status.failure(mode));
+            }
+            "-r" => {
+                let rate = number()?;
+                self.status(|status| status.rate_limit(rate));
+            }
+            "--backlog_wait_time" => {
+                let wait_time = number()?;
+                self.status(|status| status.backlog_wait_time(wait_time));
+            }
+            "-e" => match number()? {
+                enabled @ 0..=2 => self.enabled = Some(enabled),
+                enabled => {
+                    return Err(format!("invalid argument {enabled} for -e"))
+                }
+            },
+            _ => {
+                let rule = parse_rule(line).map_err(|e| e.to_string())?;
+                self.rules.push(rule);
+                return Ok(());
+            }
+        }
+        match tokens.next() {
+            Some(token) => Err(format!("unexpected argument {token:?}")),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_text(
+        &mut self,
+        text: &str,
+        file: Option<&Path>,
+    ) -> Result<(), Error> {
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.parse_line(line).map_err(|e| {
+                Error::InvalidRule(match file {
+                    Some(file) => {
+                        format!("{}:{}: {e}", file.display(), index + 1)
+                    }
+                    None => format!("line {}: {e}", index + 1),
+                })
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the content of an `audit.rules` file: one rule or directive per
+/// line, in the auditctl syntax. Empty lines and comments are skipped.
+///
+/// Besides the rules (see [`parse_rule`]), the `-D`, `-b`, `-f`, `-r`,
+/// `-e` and `--backlog_wait_time` directives are supported. Errors are
+/// reported as [`Error::InvalidRule`], with the number of the line.
+pub fn parse_rules(text: &str) -> Result<RulesPlan, Error> {
+    let mut plan = RulesPlan::default();
+    plan.parse_text(text, None)?;
+    Ok(plan)
+}
+
+/// Load the `*.rules` files of a directory such as `/etc/audit/rules.d`,
+/// in lexical order, like `augenrules` does. See [`parse_rules`].
+pub fn load_rules_dir(path: &Path) -> Result<RulesPlan, Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "rules") && path.is_file()
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    let mut plan = RulesPlan::default();
+    for file in files {
+        let text = fs::read_to_string(&file)?;
+        plan.parse_text(&text, Some(&file))?;
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        RuleMessageExt, AUDIT_STATUS_BACKLOG_LIMIT, AUDIT_STATUS_FAILURE,
+        AUDIT_STATUS_RATE_LIMIT,
+    };
+
+    const RULES: &str = "\
+## First rule - delete all
+-D
+
+# Increase the buffers to survive stress events
+-b 8192
+-f 1
+-r 100
+
+-w /etc/passwd -p wa -k identity
+-a always,exit -F arch=b64 -S adjtimex,settimeofday -k time-change
+
+-e 2
+";
+
+    #[test]
+    fn parse_directives() {
+        let plan = parse_rules(RULES).unwrap();
+        assert!(plan.delete_all);
+        assert_eq!(plan.enabled, Some(2));
+
+        let status = plan.status.unwrap();
+        assert_eq!(
+            status.mask,
+            AUDIT_STATUS_BACKLOG_LIMIT
+                | AUDIT_STATUS_FAILURE
+                | AUDIT_STATUS_RATE_LIMIT
+        );
+        assert_eq!(status.backlog_limit, 8192);
+        assert_eq!(status.failure, 1);
+        assert_eq!(status.rate_limiting, 100);
+
+        assert_eq!(plan.rules.len(), 2);
+        assert_eq!(plan.rules[0].keys(), ["identity"]);
+        assert_eq!(plan.rules[1].keys(), ["time-change"]);
+    }
+
+    #[test]
+    fn empty_plan() {
+        assert_eq!(
+            parse_rules("\n  # nothing\n\n").unwrap(),
+            RulesPlan::default()
+        );
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        let error = |text| match parse_rules(text) {
+            Err(Error::InvalidRule(e)) => e,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(
+            error("-D\n\n-b many"),
+            "line 3: invalid argument \"many\" for -b"
+        );
+        assert_eq!(
+            error("# comment\n-e 3"),
+            "line 2: invalid argument 3 for -e"
+        );
+        assert_eq!(error("-f 7"), "line 1: Invalid failure mode 7");
+        assert_eq!(error("-D -b"), "line 1: unexpected argument \"-b\"");
+        assert!(error("-D\n-w /etc/passwd -p z").starts_with("line 2: "));
+    }
+
+    #[test]
+    fn load_directory() {
+        let dir = std::env::temp_dir()
+            .join(format!("audit-rules-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("99-finalize.rules"), "-e 1\n").unwrap();
+        fs::write(dir.join("10-base-config.rules"), "-D\n-b 8192\n").unwrap();
+        fs::write(dir.join("30-stig.rules"), RULES).unwrap();
+        fs::write(dir.join("README"), "not rules").unwrap();
+        fs::write(dir.join("50-broken.rules.disabled"), "-x").unwrap();
+
+        let plan = load_rules_dir(&dir);
+        fs::write(dir.join("40-broken.rules"), "-D\n-x\n").unwrap();
+        let broken = load_rules_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Files are applied in lexical order: the last -e wins
+        let plan = plan.unwrap();
+        assert!(plan.delete_all);
+        assert_eq!(plan.enabled, Some(1));
+        assert_eq!(plan.rules.len(), 2);
+
+        match broken {
+            Err(Error::InvalidRule(e)) => assert!(
+                e.ends_with("40-broken.rules:2: Unknown option \"-x\""),
+                "{}",
+                e
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
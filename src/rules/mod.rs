@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: MIT
+
+mod field;
+pub(crate) use self::field::*;
+
+mod display;
+pub use self::display::*;
+
+mod parse;
+pub use self::parse::*;
+
+mod perm;
+pub use self::perm::*;
+
+mod load;
+pub use self::load::*;
+
+mod builder;
+pub use self::builder::*;
+
+mod normalized;
+pub use self::normalized::*;
+
+mod ext;
+pub use self::ext::*;
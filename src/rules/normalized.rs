@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MIT
+
+use std::hash::{Hash, Hasher};
+
+use crate::{
+    packet::rules::{RuleMessage, RuleSyscalls},
+    rules::{field_type_value, without_classes, RawValue, RuleSyscallsExt},
+};
+
+/// Wrapper around [`RuleMessage`] comparing and hashing rules semantically:
+/// the order of the fields is not taken into account, since the kernel may
+/// reorder them. Neither are the syscall class bits, which the kernel
+/// replaces with the syscalls of the classes when it stores the rule.
+///
+/// This makes it possible to store rules in a `HashSet`, for instance to
+/// check whether a rule returned by `Handle::list_rules` is already there.
+#[derive(Debug, Clone)]
+pub struct NormalizedRule(pub RuleMessage);
+
+impl NormalizedRule {
+    pub fn into_inner(self) -> RuleMessage {
+        self.0
+    }
+
+    fn sorted_fields(&self) -> Vec<(u32, u32, RawValue<'_>)> {
+        let mut fields: Vec<_> = self
+            .0
+            .fields
+            .iter()
+            .map(|(field, flags)| {
+                let (field_type, value) = field_type_value(field);
+                (field_type, u32::from(*flags), value)
+            })
+            .collect();
+        fields.sort_unstable();
+        fields
+    }
+
+    fn syscalls(&self) -> RuleSyscalls {
+        without_classes(&self.0.syscalls)
+    }
+}
+
+impl From<RuleMessage> for NormalizedRule {
+    fn from(rule: RuleMessage) -> Self {
+        NormalizedRule(rule)
+    }
+}
+
+impl PartialEq for NormalizedRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.flags == other.0.flags
+            && self.0.action == other.0.action
+            && self.syscalls() == other.syscalls()
+            && self.sorted_fields() == other.sorted_fields()
+    }
+}
+
+impl Eq for NormalizedRule {}
+
+impl Hash for NormalizedRule {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        u32::from(self.0.flags).hash(state);
+        u32::from(self.0.action).hash(state);
+        for syscall in self.syscalls().iter() {
+            syscall.hash(state);
+        }
+        self.sorted_fields().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_rule, RuleMessageExt};
+
+    #[test]
+    fn ignore_field_order() {
+        let rule = parse_rule("-a always,exit -S openat -F uid=0 -F gid=0");
+        let reordered =
+            parse_rule("-a always,exit -S openat -F gid=0 -F uid=0");
+        assert!(rule.unwrap().is_equivalent(&reordered.unwrap()));
+    }
+
+    #[test]
+    fn ignore_syscall_classes() {
+        // The kernel clears the class bits of the rules it stores
+        let rule = parse_rule("-w /etc/passwd -p wa -k passwd").unwrap();
+        let mut stored = rule.clone();
+        stored.syscalls = without_classes(&rule.syscalls);
+        assert_ne!(rule, stored);
+        assert!(rule.is_equivalent(&stored));
+
+        let mut other = stored.clone();
+        other.syscalls.unset(0);
+        assert!(!rule.is_equivalent(&other));
+    }
+}
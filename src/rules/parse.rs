@@ -0,0 +1,564 @@
+// SPDX-License-Identifier: MIT
+
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::{
+    arch_for_bits, current_arch,
+    packet::{
+        constants::*,
+        rules::{
+            RuleAction, RuleField, RuleFieldFlags, RuleFlags, RuleMessage,
+            RuleSyscalls,
+        },
+    },
+    record_type_from_name,
+    rules::{action_name, arch_name, list_name, push_key, MAX_SYSCALLS},
+    syscalls, AUDIT_ARCH_RISCV64,
+};
+
+#[derive(Clone, Eq, PartialEq, Debug, Error)]
+pub enum ParseRuleError {
+    #[error("Unknown option {0:?}")]
+    UnknownOption(String),
+
+    #[error("Missing argument for option {0}")]
+    MissingArgument(String),
+
+    #[error("Invalid action or filter list {0:?}")]
+    InvalidList(String),
+
+    #[error("Invalid field {0:?}, expected <name><operator><value>")]
+    InvalidField(String),
+
+    #[error("Unknown field {0:?}")]
+    UnknownField(String),
+
+    #[error("Field {0:?} is not supported")]
+    UnsupportedField(String),
+
+    #[error("Invalid value {value:?} for field {field}")]
+    InvalidValue { field: String, value: String },
+
+    #[error("Unknown syscall {0:?}")]
+    UnknownSyscall(String),
+
+    #[error("Invalid permissions {0:?}, expected a combination of r, w, x, a")]
+    InvalidPermissions(String),
+
+    #[error("Rule has neither -a nor -w")]
+    MissingRuleType,
+
+    #[error("-F arch must come before -S")]
+    ArchAfterSyscalls,
+}
+
+/// Parse a rule written using the `auditctl` syntax, such as
+/// `-w /etc/passwd -p rwxa -k my_key` or
+/// `-a always,exit -F arch=x86_64 -S personality -F key=bypass`.
+///
+/// The following options are supported: `-a` and `-A` (`action,list` or
+/// `list,action`), `-w`, `-p`, `-S`, `-F` (with the `=`, `!=`, `<`, `>`,
+/// `<=`, `>=`, `&` and `&=` operators) and `-k`.
+///
+/// As with auditctl, `-w` watches a single file (`AUDIT_WATCH`), or the
+/// whole subtree (`AUDIT_DIR`) if the path is a directory. A path ending
+/// with `/` is a directory, and the slash is dropped. For other paths, the
+/// filesystem of the host is checked: the same rule parses to a
+/// [`RuleField::Dir`] where the directory exists, and to a
+/// [`RuleField::Watch`] elsewhere, for instance when rules are checked on
+/// another machine than the one they are loaded on. Use a trailing `/` or
+/// `-F dir=<path>` to watch a directory regardless of the host.
+///
+/// The `exe`, `field_compare` and `saddr_fam` fields are rejected with
+/// [`ParseRuleError::UnsupportedField`], since `RuleField` cannot represent
+/// them. `saddr_fam`, the address family of the socket address of a
+/// syscall, is the only network field the kernel filters on: there is no
+/// field for IP addresses or ports.
+///
+/// `-F arch` takes the name of an architecture, such as `x86_64`, or `b64`
+/// and `b32` for the native 64 and 32 bits architectures. On `x86_64`,
+/// `b32` is `i386`, the architecture of compat syscalls. Like auditctl,
+/// `-F arch` must come before `-S`, since the syscall names are resolved
+/// for the architecture of the rule. Otherwise,
+/// [`ParseRuleError::ArchAfterSyscalls`] is returned instead of silently
+/// resolving them for the native architecture.
+///
+/// ```
+/// use audit::{parse_rule, ParseRuleError};
+///
+/// assert!(parse_rule("-a always,exit -F arch=x86_64 -S openat").is_ok());
+/// assert_eq!(
+///     parse_rule("-a always,exit -S openat -F arch=x86_64"),
+///     Err(ParseRuleError::ArchAfterSyscalls),
+/// );
+/// ```
+///
+/// Together with [`RuleMessageExt::display`](crate::RuleMessageExt::display)
+/// this is the recommended way to store rules in configuration files: the
+/// rules then use the same text format as `audit.rules`.
+pub fn parse_rule(s: &str) -> Result<RuleMessage, ParseRuleError> {
+    let mut rule = RuleMessage::new();
+    let mut has_type = false;
+    let mut syscalls: Option<RuleSyscalls> = None;
+    let mut arch = None;
+
+    let mut tokens = s.split_whitespace();
+    while let Some(option) = tokens.next() {
+        let mut arg = || {
+            tokens
+                .next()
+                .ok_or_else(|| ParseRuleError::MissingArgument(option.into()))
+        };
+        match option {
+            "-a" | "-A" => {
+                let (action, list) = parse_action_list(arg()?)?;
+                rule.action = action;
+                rule.flags = if option == "-A" {
+                    RuleFlags::from(u32::from(list) | AUDIT_FILTER_PREPEND)
+                } else {
+                    list
+                };
+                has_type = true;
+            }
+            "-w" => {
+                rule.action = RuleAction::Always;
+                rule.flags = RuleFlags::FilterExit;
+                // Like auditctl, watch the whole subtree of directories,
+                // and drop the trailing slashes
+                let path = arg()?;
+                let watch = match path.trim_end_matches('/') {
+                    dir if dir.len() < path.len() && !dir.is_empty() => {
+                        RuleField::Dir(dir.into())
+                    }
+                    _ if Path::new(path).is_dir() => {
+                        RuleField::Dir(path.into())
+                    }
+                    _ => RuleField::Watch(path.into()),
+                };
+                rule.fields.push((watch, RuleFieldFlags::Equal));
+                has_type = true;
+            }
+            "-p" => {
+                let perm = parse_perm(arg()?)?;
+                rule.fields
+                    .push((RuleField::Perm(perm), RuleFieldFlags::Equal));
+            }
+            "-k" => push_key(&mut rule.fields, arg()?),
+            "-S" => {
+                let syscalls =
+                    syscalls.get_or_insert_with(RuleSyscalls::new_zeroed);
+                for name in arg()?.split(',') {
+                    if name == "all" {
+                        syscalls.set_all();
+                    } else {
+                        syscalls.set(parse_syscall(arch, name)?);
+                    }
+                }
+            }
+            "-F" => {
+                let (field, flags) = parse_field(arg()?)?;
+                if let RuleField::Arch(value) = field {
+                    if syscalls.is_some() {
+                        return Err(ParseRuleError::ArchAfterSyscalls);
+                    }
+                    arch = Some(value);
+                }
+                match (field, flags) {
+                    (RuleField::Filterkey(key), RuleFieldFlags::Equal) => {
+                        push_key(&mut rule.fields, &key)
+                    }
+                    (field, flags) => rule.fields.push((field, flags)),
+                }
+            }
+            _ => return Err(ParseRuleError::UnknownOption(option.into())),
+        }
+    }
+
+    if !has_type {
+        return Err(ParseRuleError::MissingRuleType);
+    }
+    rule.syscalls = syscalls.unwrap_or_else(RuleSyscalls::new_maxed);
+    Ok(rule)
+}
+
+fn parse_action_list(
+    s: &str,
+) -> Result<(RuleAction, RuleFlags), ParseRuleError> {
+    let invalid = || ParseRuleError::InvalidList(s.into());
+    let (first, second) = s.split_once(',').ok_or_else(invalid)?;
+    let (action, list) = match (parse_action(first), parse_list(second)) {
+        (Some(action), Some(list)) => (action, list),
+        _ => (
+            parse_action(second).ok_or_else(invalid)?,
+            parse_list(first).ok_or_else(invalid)?,
+        ),
+    };
+    Ok((action, list))
+}
+
+fn parse_action(s: &str) -> Option<RuleAction> {
+    [RuleAction::Never, RuleAction::Possible, RuleAction::Always]
+        .iter()
+        .copied()
+        .find(|action| action_name(*action) == Some(s))
+}
+
+fn parse_list(s: &str) -> Option<RuleFlags> {
+    [
+        RuleFlags::FilterUser,
+        RuleFlags::FilterTask,
+        RuleFlags::FilterEntry,
+        RuleFlags::FilterExit,
+        RuleFlags::FilterType,
+        RuleFlags::FilterFs,
+    ]
+    .iter()
+    .copied()
+    .find(|list| list_name(*list) == Some(s))
+}
+
+pub(crate) fn parse_perm(s: &str) -> Result<u32, ParseRuleError> {
+    let mut perm = 0;
+    for c in s.chars() {
+        perm |= match c {
+            'r' => AUDIT_PERM_READ,
+            'w' => AUDIT_PERM_WRITE,
+            'x' => AUDIT_PERM_EXEC,
+            'a' => AUDIT_PERM_ATTR,
+            _ => return Err(ParseRuleError::InvalidPermissions(s.into())),
+        };
+    }
+    if perm == 0 {
+        return Err(ParseRuleError::InvalidPermissions(s.into()));
+    }
+    Ok(perm)
+}
+
+/// Parse a syscall name or number. Names are resolved for `arch`, or the
+/// native architecture if the rule does not have an `arch` field. Numbers
+/// that do not fit in the syscalls bitmap are rejected.
+pub(crate) fn parse_syscall(
+    arch: Option<u32>,
+    name: &str,
+) -> Result<u32, ParseRuleError> {
+    name.parse::<u32>()
+        .ok()
+        .or_else(|| syscalls::resolve(arch.unwrap_or_else(current_arch), name))
+        .filter(|syscall| *syscall < MAX_SYSCALLS)
+        .ok_or_else(|| ParseRuleError::UnknownSyscall(name.into()))
+}
+
+fn parse_operator(s: &str) -> Option<(&str, RuleFieldFlags, &str)> {
+    // Two characters operators must be tried first
+    const OPERATORS: &[(&str, RuleFieldFlags)] = &[
+        ("!=", RuleFieldFlags::NotEqual),
+        ("<=", RuleFieldFlags::LessThanOrEqual),
+        (">=", RuleFieldFlags::GreaterThanOrEqual),
+        ("&=", RuleFieldFlags::BitTest),
+        ("=", RuleFieldFlags::Equal),
+        ("<", RuleFieldFlags::LessThan),
+        (">", RuleFieldFlags::GreaterThan),
+        ("&", RuleFieldFlags::BitMask),
+    ];
+    let start = s.find(['!', '<', '>', '&', '='])?;
+    let (name, rest) = s.split_at(start);
+    OPERATORS.iter().find_map(|(op, flags)| {
+        rest.strip_prefix(op).map(|value| (name, *flags, value))
+    })
+}
+
+/// Parse a `-F` argument, such as `exit!=0`.
+///
+/// Numeric fields (ids, `exit`, `success`, `msgtype`...) are parsed as
+/// `u32`. Like auditctl, `exit` also accepts errno names such as `-EACCES`,
+/// and `msgtype` record type names such as `CONFIG_CHANGE`.
+/// Path or label fields (`path`, `dir`, `key`, `obj_user`...) as
+/// strings. `exe` is rejected: `netlink-packet-audit` stores it as a number
+/// whereas the kernel expects a path, so it would not round-trip. The same
+/// goes for `field_compare`, which auditctl expresses with `-C`.
+pub(crate) fn parse_field(
+    s: &str,
+) -> Result<(RuleField, RuleFieldFlags), ParseRuleError> {
+    use crate::packet::rules::RuleField::*;
+
+    let (name, flags, value) = parse_operator(s)
+        .filter(|(name, _, value)| !name.is_empty() && !value.is_empty())
+        .ok_or_else(|| ParseRuleError::InvalidField(s.into()))?;
+
+    let invalid = || ParseRuleError::InvalidValue {
+        field: name.into(),
+        value: value.into(),
+    };
+    let number = || parse_number(value).ok_or_else(invalid);
+    let id = || {
+        if value == "unset" {
+            Ok(u32::MAX)
+        } else {
+            number()
+        }
+    };
+    let string = || value.to_string();
+
+    let field = match name {
+        "pid" => Pid(number()?),
+        "uid" => Uid(id()?),
+        "euid" => Euid(id()?),
+        "suid" => Suid(id()?),
+        "fsuid" => Fsuid(id()?),
+        "gid" => Gid(id()?),
+        "egid" => Egid(id()?),
+        "sgid" => Sgid(id()?),
+        "fsgid" => Fsgid(id()?),
+        "auid" | "loginuid" => Loginuid(id()?),
+        "pers" => Pers(number()?),
+        "arch" => Arch(parse_arch(value).ok_or_else(invalid)?),
+        "msgtype" => Msgtype(match record_type_from_name(value) {
+            Some(message_type) => message_type.into(),
+            None => number()?,
+        }),
+        "ppid" => Ppid(number()?),
+        "loginuid_set" => LoginuidSet(number()?),
+        "sessionid" => Sessionid(id()?),
+        "fstype" => Fstype(number()?),
+        "devmajor" => Devmajor(number()?),
+        "devminor" => Devminor(number()?),
+        "inode" => Inode(number()?),
+        "exit" => Exit(parse_exit(value).ok_or_else(invalid)?),
+        "success" => Success(number()?),
+        "perm" => Perm(parse_perm(value)?),
+        "filetype" => Filetype(number()?),
+        "obj_uid" => ObjUid(id()?),
+        "obj_gid" => ObjGid(id()?),
+        "a0" => Arg0(number()?),
+        "a1" => Arg1(number()?),
+        "a2" => Arg2(number()?),
+        "a3" => Arg3(number()?),
+        "path" => Watch(string()),
+        "dir" => Dir(string()),
+        "key" => Filterkey(string()),
+        "subj_user" => SubjUser(string()),
+        "subj_role" => SubjRole(string()),
+        "subj_type" => SubjType(string()),
+        "subj_sen" => SubjSen(string()),
+        "subj_clr" => SubjClr(string()),
+        "obj_user" => ObjUser(string()),
+        "obj_role" => ObjRole(string()),
+        "obj_type" => ObjType(string()),
+        "obj_lev_low" => ObjLevLow(string()),
+        "obj_lev_high" => ObjLevHigh(string()),
+        "exe" | "field_compare" | "saddr_fam" => {
+            return Err(ParseRuleError::UnsupportedField(name.into()))
+        }
+        _ => return Err(ParseRuleError::UnknownField(name.into())),
+    };
+    Ok((field, flags))
+}
+
+/// Parse a decimal, hexadecimal (`0x` prefix) or negative decimal number.
+/// Negative numbers are stored in two's complement, like the kernel does
+/// for the `exit` field.
+fn parse_number(s: &str) -> Option<u32> {
+    if let Some(hex) = s.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if s.starts_with('-') {
+        s.parse::<i32>().ok().map(|n| n as u32)
+    } else {
+        s.parse::<u32>().ok()
+    }
+}
+
+/// Parse the value of an `exit` field, either a number or an errno name
+/// such as `-EPERM`
+fn parse_exit(s: &str) -> Option<u32> {
+    const ERRNOS: &[(&str, i32)] = &[
+        ("EPERM", libc::EPERM),
+        ("ENOENT", libc::ENOENT),
+        ("ESRCH", libc::ESRCH),
+        ("EINTR", libc::EINTR),
+        ("EIO", libc::EIO),
+        ("ENXIO", libc::ENXIO),
+        ("E2BIG", libc::E2BIG),
+        ("ENOEXEC", libc::ENOEXEC),
+        ("EBADF", libc::EBADF),
+        ("ECHILD", libc::ECHILD),
+        ("EAGAIN", libc::EAGAIN),
+        ("ENOMEM", libc::ENOMEM),
+        ("EACCES", libc::EACCES),
+        ("EFAULT", libc::EFAULT),
+        ("EBUSY", libc::EBUSY),
+        ("EEXIST", libc::EEXIST),
+        ("EXDEV", libc::EXDEV),
+        ("ENODEV", libc::ENODEV),
+        ("ENOTDIR", libc::ENOTDIR),
+        ("EISDIR", libc::EISDIR),
+        ("EINVAL", libc::EINVAL),
+        ("ENFILE", libc::ENFILE),
+        ("EMFILE", libc::EMFILE),
+        ("ENOTTY", libc::ENOTTY),
+        ("ETXTBSY", libc::ETXTBSY),
+        ("EFBIG", libc::EFBIG),
+        ("ENOSPC", libc::ENOSPC),
+        ("ESPIPE", libc::ESPIPE),
+        ("EROFS", libc::EROFS),
+        ("EMLINK", libc::EMLINK),
+        ("EPIPE", libc::EPIPE),
+        ("ENAMETOOLONG", libc::ENAMETOOLONG),
+        ("ENOSYS", libc::ENOSYS),
+        ("ENOTEMPTY", libc::ENOTEMPTY),
+        ("ELOOP", libc::ELOOP),
+        ("EOPNOTSUPP", libc::EOPNOTSUPP),
+    ];
+    let (sign, name) = match s.strip_prefix('-') {
+        Some(name) => (-1, name),
+        None => (1, s),
+    };
+    match ERRNOS.iter().find(|(n, _)| *n == name) {
+        Some((_, errno)) => Some((sign * errno) as u32),
+        None => parse_number(s),
+    }
+}
+
+fn parse_arch(s: &str) -> Option<u32> {
+    const ARCHS: &[u32] = &[
+        AUDIT_ARCH_X86_64,
+        AUDIT_ARCH_I386,
+        AUDIT_ARCH_AARCH64,
+        AUDIT_ARCH_ARM,
+        AUDIT_ARCH_PPC,
+        AUDIT_ARCH_PPC64,
+        AUDIT_ARCH_PPC64LE,
+        AUDIT_ARCH_S390,
+        AUDIT_ARCH_S390X,
+        AUDIT_ARCH_IA64,
+        AUDIT_ARCH_RISCV64,
+    ];
+    match s {
+        "b64" => return arch_for_bits(64),
+        "b32" => return arch_for_bits(32),
+        _ => {}
+    }
+    ARCHS
+        .iter()
+        .copied()
+        .find(|arch| arch_name(*arch) == Some(s))
+        .or_else(|| parse_number(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arch_of(rule: &RuleMessage) -> Option<u32> {
+        rule.fields.iter().find_map(|(field, _)| match field {
+            RuleField::Arch(arch) => Some(*arch),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn arch_names() {
+        let rule =
+            parse_rule("-a always,exit -F arch=aarch64 -S openat").unwrap();
+        assert_eq!(arch_of(&rule), Some(AUDIT_ARCH_AARCH64));
+        assert!(rule.syscalls.has(56));
+        assert_eq!(
+            parse_rule("-a always,exit -F arch=vax"),
+            Err(ParseRuleError::InvalidValue {
+                field: "arch".into(),
+                value: "vax".into(),
+            })
+        );
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn arch_bits() {
+        let rule = parse_rule(
+            "-a always,exit -F arch=b64 -S adjtimex,settimeofday -k time",
+        )
+        .unwrap();
+        assert_eq!(arch_of(&rule), Some(AUDIT_ARCH_X86_64));
+        assert!(rule.syscalls.has(159) && rule.syscalls.has(164));
+
+        // Compat syscalls have their own numbers
+        let rule = parse_rule(
+            "-a always,exit -F arch=b32 -S adjtimex,settimeofday,stime -k time",
+        )
+        .unwrap();
+        assert_eq!(arch_of(&rule), Some(AUDIT_ARCH_I386));
+        assert!(
+            rule.syscalls.has(124)
+                && rule.syscalls.has(79)
+                && rule.syscalls.has(25)
+        );
+    }
+
+    #[test]
+    fn operators_round_trip() {
+        use RuleFieldFlags::*;
+        for flags in [
+            Equal,
+            NotEqual,
+            LessThan,
+            GreaterThan,
+            LessThanOrEqual,
+            GreaterThanOrEqual,
+            BitMask,
+            BitTest,
+        ] {
+            let op = crate::rules::operator_str(flags);
+            let field = format!("uid{op}1000");
+            assert_eq!(parse_operator(&field), Some(("uid", flags, "1000")));
+
+            let line = format!("-a always,exit -S all -F {field}");
+            let rule = parse_rule(&line).unwrap();
+            assert_eq!(rule.fields, [(RuleField::Uid(1000), flags)]);
+            assert_eq!(crate::RuleMessageExt::display(&rule).to_string(), line);
+        }
+        assert!(parse_operator("uid~1000").is_none());
+    }
+
+    #[test]
+    fn watch_directories() {
+        fn watch(line: &str) -> RuleField {
+            parse_rule(line).unwrap().fields.remove(0).0
+        }
+        let missing = std::env::temp_dir().join("audit-rs-missing-dir");
+        let missing = missing.to_str().unwrap();
+        assert_eq!(
+            watch(&format!("-w {missing}/ -p wa")),
+            RuleField::Dir(missing.into())
+        );
+        assert_eq!(
+            watch(&format!("-w {missing}// -p wa")),
+            RuleField::Dir(missing.into())
+        );
+        assert_eq!(
+            watch(&format!("-w {missing} -p wa")),
+            RuleField::Watch(missing.into())
+        );
+        assert_eq!(watch("-w / -p wa"), RuleField::Dir("/".into()));
+    }
+
+    #[test]
+    fn arch_after_syscalls() {
+        assert_eq!(
+            parse_rule("-a always,exit -S openat -F arch=aarch64"),
+            Err(ParseRuleError::ArchAfterSyscalls)
+        );
+        assert_eq!(
+            parse_rule("-a always,exit -F arch=aarch64 -S openat -F arch=arm"),
+            Err(ParseRuleError::ArchAfterSyscalls)
+        );
+        // Other fields can come after the syscalls
+        let rule = parse_rule(
+            "-a always,exit -F arch=aarch64 -S openat -F exit=-EACCES -k k",
+        )
+        .unwrap();
+        assert_eq!(arch_of(&rule), Some(AUDIT_ARCH_AARCH64));
+        // Without syscalls, the position of arch does not matter
+        assert!(parse_rule("-a always,exit -F uid=0 -F arch=aarch64").is_ok());
+    }
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MIT
+
+use std::{
+    fmt,
+    ops::{BitOr, BitOrAssign},
+    str::FromStr,
+};
+
+use crate::{
+    packet::{
+        constants::{
+            AUDIT_PERM_ATTR, AUDIT_PERM_EXEC, AUDIT_PERM_READ, AUDIT_PERM_WRITE,
+        },
+        rules::RuleField,
+    },
+    rules::{parse_perm, perm_str},
+    ParseRuleError,
+};
+
+/// Permissions triggering a watch, the value of `RuleField::Perm`.
+///
+/// ```
+/// use audit::{packet::rules::RuleField, PermFlags};
+///
+/// let perm = PermFlags::READ | PermFlags::WRITE;
+/// assert_eq!(RuleField::from(perm), RuleField::Perm(6));
+/// assert_eq!("rwxa".parse::<PermFlags>(), Ok(PermFlags::ALL));
+/// assert_eq!(PermFlags::ALL.to_string(), "rwxa");
+/// ```
+#[derive(Copy, Debug, PartialEq, Eq, Clone, Default)]
+pub struct PermFlags(pub u32);
+
+impl PermFlags {
+    pub const READ: PermFlags = PermFlags(AUDIT_PERM_READ);
+    pub const WRITE: PermFlags = PermFlags(AUDIT_PERM_WRITE);
+    pub const EXEC: PermFlags = PermFlags(AUDIT_PERM_EXEC);
+    /// Changes of the attributes of the file, such as its owner or mode
+    pub const ATTR: PermFlags = PermFlags(AUDIT_PERM_ATTR);
+    pub const ALL: PermFlags = PermFlags(
+        AUDIT_PERM_READ | AUDIT_PERM_WRITE | AUDIT_PERM_EXEC | AUDIT_PERM_ATTR,
+    );
+
+    /// Return `true` if all the permissions of `other` are set
+    pub fn contains(self, other: PermFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for PermFlags {
+    type Output = PermFlags;
+
+    fn bitor(self, other: PermFlags) -> PermFlags {
+        PermFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for PermFlags {
+    fn bitor_assign(&mut self, other: PermFlags) {
+        self.0 |= other.0;
+    }
+}
+
+impl From<PermFlags> for u32 {
+    fn from(perm: PermFlags) -> Self {
+        perm.0
+    }
+}
+
+impl From<PermFlags> for RuleField {
+    fn from(perm: PermFlags) -> Self {
+        RuleField::Perm(perm.0)
+    }
+}
+
+impl FromStr for PermFlags {
+    type Err = ParseRuleError;
+
+    /// Parse permissions written as for `auditctl -p`, such as `wa`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_perm(s).map(PermFlags)
+    }
+}
+
+/// Render the permissions as for `auditctl -p`
+impl fmt::Display for PermFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&perm_str(self.0))
+    }
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: MIT
+
+#[cfg(any(feature = "tokio_socket", feature = "smol_socket"))]
+use std::{convert::TryInto, time::Duration};
+
+#[cfg(any(feature = "tokio_socket", feature = "smol_socket"))]
+use crate::Error;
+
+#[cfg(any(feature = "tokio_socket", feature = "smol_socket"))]
+const SIGNAL_INFO_HEADER_LEN: usize = 8;
+
+/// Sender of the last signal to the audit daemon, as returned by
+/// `AUDIT_SIGNAL_INFO` (`struct audit_sig_info`)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SignalInfo {
+    /// Login uid of the sender
+    pub uid: u32,
+    pub pid: u32,
+    /// Security context of the sender, if an LSM is enabled
+    pub ctx: Option<String>,
+}
+
+#[cfg(any(feature = "tokio_socket", feature = "smol_socket"))]
+impl SignalInfo {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < SIGNAL_INFO_HEADER_LEN {
+            return None;
+        }
+        let uid = u32::from_ne_bytes(bytes[0..4].try_into().unwrap());
+        let pid = u32::from_ne_bytes(bytes[4..8].try_into().unwrap());
+        let ctx = bytes[SIGNAL_INFO_HEADER_LEN..]
+            .split(|b| *b == 0)
+            .next()
+            .filter(|ctx| !ctx.is_empty())
+            .map(|ctx| String::from_utf8_lossy(ctx).into_owned());
+        Some(SignalInfo { uid, pid, ctx })
+    }
+}
+
+/// Socket of the runtime matching the enabled socket feature, like
+/// `crate::time::sleep`
+#[cfg(feature = "tokio_socket")]
+type RawSocket = crate::sys::TokioSocket;
+#[cfg(all(feature = "smol_socket", not(feature = "tokio_socket")))]
+type RawSocket = crate::sys::SmolSocket;
+
+/// Send an `AUDIT_SIGNAL_INFO` request on a short-lived socket, and decode
+/// the reply from its raw bytes.
+///
+/// The connection of the `Handle` cannot be used: its codec drops the
+/// replies that are not valid UTF-8, such as this one when the uid or the
+/// pid has a byte above 127, and since the kernel does not acknowledge the
+/// request, the reply would be waited for forever.
+#[cfg(any(feature = "tokio_socket", feature = "smol_socket"))]
+pub(crate) async fn request_signal_info(
+    timeout: Option<Duration>,
+) -> Result<SignalInfo, Error> {
+    use futures::future::{self, Either};
+
+    let exchange = Box::pin(exchange());
+    match timeout {
+        Some(timeout) => {
+            match future::select(exchange, crate::time::sleep(timeout)).await {
+                Either::Left((reply, _)) => reply,
+                Either::Right(_) => Err(Error::Timeout),
+            }
+        }
+        None => exchange.await,
+    }
+}
+
+#[cfg(any(feature = "tokio_socket", feature = "smol_socket"))]
+async fn exchange() -> Result<SignalInfo, Error> {
+    use futures::future::poll_fn;
+    use netlink_packet_core::{
+        NetlinkBuffer, NetlinkMessage, NetlinkPayload, NLMSG_ERROR,
+        NLM_F_REQUEST,
+    };
+
+    use crate::{
+        packet::{constants::AUDIT_SIGNAL_INFO, AuditMessage},
+        sys::{protocols::NETLINK_AUDIT, AsyncSocket, SocketAddr},
+    };
+
+    let invalid = |e: String| {
+        Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    };
+
+    let socket = RawSocket::new(NETLINK_AUDIT)?;
+    let mut req = NetlinkMessage::from(AuditMessage::Other((
+        AUDIT_SIGNAL_INFO,
+        String::new(),
+    )));
+    req.header.flags = NLM_F_REQUEST;
+    req.finalize();
+    let mut buf = vec![0; req.buffer_len()];
+    req.serialize(&mut buf);
+    let kernel = SocketAddr::new(0, 0);
+    poll_fn(|cx| socket.poll_send_to(cx, &buf, &kernel)).await?;
+
+    loop {
+        let (datagram, _) =
+            poll_fn(|cx| socket.poll_recv_from_full(cx)).await?;
+        let mut datagram = &datagram[..];
+        while !datagram.is_empty() {
+            let message = NetlinkBuffer::new_checked(datagram)
+                .map_err(|e| invalid(e.to_string()))?;
+            let len = message.length() as usize;
+            match message.message_type() {
+                AUDIT_SIGNAL_INFO => {
+                    return SignalInfo::from_bytes(message.payload())
+                        .ok_or(Error::RequestFailed);
+                }
+                NLMSG_ERROR => {
+                    let reply = NetlinkMessage::<AuditMessage>::deserialize(
+                        &datagram[..len],
+                    )
+                    .map_err(|e| invalid(e.to_string()))?;
+                    if let NetlinkPayload::Error(err_msg) = reply.payload {
+                        if err_msg.code.is_some() {
+                            return Err(Error::from(err_msg));
+                        }
+                    }
+                }
+                _ => {}
+            }
+            // Messages are aligned on 4 bytes
+            datagram = &datagram[((len + 3) & !3).min(datagram.len())..];
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tokio_socket"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_non_utf8_reply() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&1000u32.to_ne_bytes());
+        bytes.extend_from_slice(&0x1_00ffu32.to_ne_bytes());
+        bytes.extend_from_slice(b"system_u:system_r:init_t:s0\0");
+        assert!(std::str::from_utf8(&bytes).is_err());
+        assert_eq!(
+            SignalInfo::from_bytes(&bytes),
+            Some(SignalInfo {
+                uid: 1000,
+                pid: 0x1_00ff,
+                ctx: Some("system_u:system_r:init_t:s0".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn decode_without_context() {
+        let bytes = [0xff, 0xff, 0xff, 0xff, 1, 0, 0, 0];
+        let info = SignalInfo::from_bytes(&bytes).unwrap();
+        assert_eq!(info.uid, u32::MAX);
+        assert_eq!(info.ctx, None);
+        assert_eq!(SignalInfo::from_bytes(&bytes[..7]), None);
+    }
+}
@@ -0,0 +1,356 @@
+// SPDX-License-Identifier: MIT
+
+use std::convert::TryFrom;
+
+use crate::{
+    packet::{
+        constants::{AUDIT_FAIL_PANIC, AUDIT_FAIL_PRINTK, AUDIT_FAIL_SILENT},
+        StatusMessage,
+    },
+    Error, AUDIT_FEATURE_BITMAP_BACKLOG_LIMIT,
+    AUDIT_FEATURE_BITMAP_BACKLOG_WAIT_TIME,
+    AUDIT_FEATURE_BITMAP_EXCLUDE_EXTEND, AUDIT_FEATURE_BITMAP_EXECUTABLE_PATH,
+    AUDIT_FEATURE_BITMAP_FILTER_FS, AUDIT_FEATURE_BITMAP_LOST_RESET,
+    AUDIT_FEATURE_BITMAP_SESSIONID_FILTER, AUDIT_STATUS_BACKLOG_LIMIT,
+    AUDIT_STATUS_BACKLOG_WAIT_TIME, AUDIT_STATUS_ENABLED, AUDIT_STATUS_FAILURE,
+    AUDIT_STATUS_PID, AUDIT_STATUS_RATE_LIMIT,
+};
+
+/// Value of `StatusMessage::enabled` when the configuration is locked
+const AUDIT_ENABLED_LOCKED: u32 = 2;
+
+/// What the kernel does when it fails to record an audit message, for
+/// instance when the backlog is full.
+#[derive(Copy, Debug, PartialEq, Eq, Clone)]
+pub enum FailureMode {
+    /// Silently drop the message
+    Silent,
+    /// Log the message with `printk`
+    Printk,
+    /// Panic the kernel
+    Panic,
+}
+
+impl From<FailureMode> for u32 {
+    fn from(value: FailureMode) -> Self {
+        use self::FailureMode::*;
+        match value {
+            Silent => AUDIT_FAIL_SILENT,
+            Printk => AUDIT_FAIL_PRINTK,
+            Panic => AUDIT_FAIL_PANIC,
+        }
+    }
+}
+
+impl TryFrom<u32> for FailureMode {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        use self::FailureMode::*;
+        match value {
+            AUDIT_FAIL_SILENT => Ok(Silent),
+            AUDIT_FAIL_PRINTK => Ok(Printk),
+            AUDIT_FAIL_PANIC => Ok(Panic),
+            _ => Err(Error::InvalidFailureMode(value)),
+        }
+    }
+}
+
+/// A kernel capability advertised in `StatusMessage::feature_bitmap`
+#[derive(Copy, Debug, PartialEq, Eq, Clone)]
+pub enum AuditFeature {
+    /// `StatusMessage::backlog_limit` can be set
+    BacklogLimit,
+    /// `StatusMessage::backlog_wait_time` can be set
+    BacklogWaitTime,
+    /// Rules can filter on `RuleField::Exe`
+    ExecutablePath,
+    /// The exclude list supports more fields than `msgtype`
+    ExcludeExtend,
+    /// Rules can filter on `RuleField::Sessionid`
+    SessionidFilter,
+    /// `Handle::reset_lost` is supported
+    LostReset,
+    /// The filesystem filter list is supported
+    FilterFs,
+}
+
+impl From<AuditFeature> for u32 {
+    fn from(value: AuditFeature) -> Self {
+        use self::AuditFeature::*;
+        match value {
+            BacklogLimit => AUDIT_FEATURE_BITMAP_BACKLOG_LIMIT,
+            BacklogWaitTime => AUDIT_FEATURE_BITMAP_BACKLOG_WAIT_TIME,
+            ExecutablePath => AUDIT_FEATURE_BITMAP_EXECUTABLE_PATH,
+            ExcludeExtend => AUDIT_FEATURE_BITMAP_EXCLUDE_EXTEND,
+            SessionidFilter => AUDIT_FEATURE_BITMAP_SESSIONID_FILTER,
+            LostReset => AUDIT_FEATURE_BITMAP_LOST_RESET,
+            FilterFs => AUDIT_FEATURE_BITMAP_FILTER_FS,
+        }
+    }
+}
+
+/// Features supported by the kernel, as reported by
+/// `StatusMessage::feature_bitmap`. The bits are the
+/// `AUDIT_FEATURE_BITMAP_*` constants.
+#[derive(Copy, Debug, PartialEq, Eq, Clone, Default)]
+pub struct FeatureBitmap(pub u32);
+
+impl FeatureBitmap {
+    /// Return `true` if all the given `AUDIT_FEATURE_BITMAP_*` bits are set
+    pub fn contains(self, bits: u32) -> bool {
+        self.0 & bits == bits
+    }
+
+    pub fn supports(self, feature: AuditFeature) -> bool {
+        self.contains(feature.into())
+    }
+}
+
+/// Typed accessors for the fields of [`StatusMessage`]
+pub trait StatusMessageExt {
+    /// Return `true` if auditing is enabled, including when it is locked
+    fn is_enabled(&self) -> bool;
+
+    /// Return `true` if the audit configuration is locked (`auditctl -e 2`),
+    /// in which case it cannot be changed until the next reboot
+    fn is_locked(&self) -> bool;
+
+    fn failure_mode(&self) -> Result<FailureMode, Error>;
+
+    fn features(&self) -> FeatureBitmap;
+
+    /// Return `true` if the kernel advertises the feature. Settings the
+    /// kernel does not support are silently ignored, so check them first.
+    ///
+    /// ```
+    /// use audit::{packet::StatusMessage, AuditFeature, StatusMessageExt};
+    ///
+    /// let mut status = StatusMessage::new();
+    /// // Linux 4.3 supports the backlog limit and wait time, and executable
+    /// // path filters
+    /// status.feature_bitmap = 0x7;
+    /// assert!(status.supports(AuditFeature::BacklogWaitTime));
+    /// assert!(status.supports(AuditFeature::ExecutablePath));
+    /// assert!(!status.supports(AuditFeature::LostReset));
+    /// ```
+    fn supports(&self, feature: AuditFeature) -> bool;
+}
+
+impl StatusMessageExt for StatusMessage {
+    fn is_enabled(&self) -> bool {
+        self.enabled != 0
+    }
+
+    fn is_locked(&self) -> bool {
+        self.enabled == AUDIT_ENABLED_LOCKED
+    }
+
+    fn failure_mode(&self) -> Result<FailureMode, Error> {
+        FailureMode::try_from(self.failure)
+    }
+
+    fn features(&self) -> FeatureBitmap {
+        FeatureBitmap(self.feature_bitmap)
+    }
+
+    fn supports(&self, feature: AuditFeature) -> bool {
+        self.features().supports(feature)
+    }
+}
+
+/// Counters and settings of the kernel audit subsystem, suitable for
+/// monitoring. See `Handle::metrics`.
+///
+/// The `backlog_wait_time_actual` counter of Linux 5.9 and later, the time
+/// processes spent waiting for room in the backlog, is not available:
+/// `netlink-packet-audit` does not parse it.
+///
+/// ```
+/// use audit::{packet::StatusMessage, AuditMetrics};
+///
+/// let mut status = StatusMessage::new();
+/// status.enabled = 1;
+/// status.lost = 3;
+/// status.backlog = 10;
+/// status.backlog_limit = 8192;
+///
+/// let metrics = AuditMetrics::from(&status);
+/// assert!(metrics.enabled && !metrics.locked);
+/// assert_eq!((metrics.lost, metrics.backlog), (3, 10));
+/// // Room left in the backlog before records are dropped
+/// assert_eq!(metrics.backlog_limit - metrics.backlog, 8182);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct AuditMetrics {
+    pub enabled: bool,
+    /// Whether the configuration is locked until the next reboot
+    pub locked: bool,
+    /// PID of the process receiving the events, 0 if there is none
+    pub pid: u32,
+    /// Number of records lost since boot (or since `Handle::reset_lost`)
+    /// because the backlog was full, the rate limit was hit or the kernel
+    /// ran out of memory
+    pub lost: u32,
+    /// Number of records currently waiting to be sent to the audit daemon
+    pub backlog: u32,
+    /// Maximum number of records waiting to be sent, 0 for no limit
+    pub backlog_limit: u32,
+    /// How long a process waits for room in the backlog, in jiffies of the
+    /// kernel (`CONFIG_HZ`, not the `USER_HZ` of `sysconf(_SC_CLK_TCK)`).
+    /// The default is 60 seconds, 0 means not waiting at all.
+    pub backlog_wait_time: u32,
+    /// Maximum number of records per second, 0 for no limit
+    pub rate_limit: u32,
+}
+
+impl From<&StatusMessage> for AuditMetrics {
+    fn from(status: &StatusMessage) -> Self {
+        AuditMetrics {
+            enabled: status.is_enabled(),
+            locked: status.is_locked(),
+            pid: status.pid,
+            lost: status.lost,
+            backlog: status.backlog,
+            backlog_limit: status.backlog_limit,
+            backlog_wait_time: status.backlog_wait_time,
+            rate_limit: status.rate_limiting,
+        }
+    }
+}
+
+/// Builder for the [`StatusMessage`] sent with `Handle::set_status`. Each
+/// setter also sets the corresponding bit of `StatusMessage::mask`, so that
+/// the kernel only changes the fields that were set.
+#[derive(Debug, Clone)]
+pub struct StatusMessageBuilder(StatusMessage);
+
+impl Default for StatusMessageBuilder {
+    fn default() -> Self {
+        StatusMessageBuilder(StatusMessage::new())
+    }
+}
+
+/// Start from an existing status message, keeping its mask
+impl From<StatusMessage> for StatusMessageBuilder {
+    fn from(status: StatusMessage) -> Self {
+        StatusMessageBuilder(status)
+    }
+}
+
+impl StatusMessageBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.0.enabled = u32::from(enabled);
+        self.0.mask |= AUDIT_STATUS_ENABLED;
+        self
+    }
+
+    /// Enable auditing and lock the configuration until the next reboot
+    pub fn locked(mut self) -> Self {
+        self.0.enabled = AUDIT_ENABLED_LOCKED;
+        self.0.mask |= AUDIT_STATUS_ENABLED;
+        self
+    }
+
+    pub fn failure(mut self, mode: FailureMode) -> Self {
+        self.0.failure = mode.into();
+        self.0.mask |= AUDIT_STATUS_FAILURE;
+        self
+    }
+
+    /// Set the PID to which audit messages should be addressed
+    pub fn pid(mut self, pid: u32) -> Self {
+        self.0.pid = pid;
+        self.0.mask |= AUDIT_STATUS_PID;
+        self
+    }
+
+    /// Set the maximum number of messages per second, `0` meaning no limit
+    pub fn rate_limit(mut self, messages_per_second: u32) -> Self {
+        self.0.rate_limiting = messages_per_second;
+        self.0.mask |= AUDIT_STATUS_RATE_LIMIT;
+        self
+    }
+
+    pub fn backlog_limit(mut self, limit: u32) -> Self {
+        self.0.backlog_limit = limit;
+        self.0.mask |= AUDIT_STATUS_BACKLOG_LIMIT;
+        self
+    }
+
+    /// Set the time to wait when the backlog is full, in jiffies
+    pub fn backlog_wait_time(mut self, wait_time: u32) -> Self {
+        self.0.backlog_wait_time = wait_time;
+        self.0.mask |= AUDIT_STATUS_BACKLOG_WAIT_TIME;
+        self
+    }
+
+    pub fn build(self) -> StatusMessage {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failure_mode_from_u32() {
+        assert_eq!(FailureMode::try_from(0), Ok(FailureMode::Silent));
+        assert_eq!(FailureMode::try_from(1), Ok(FailureMode::Printk));
+        assert_eq!(FailureMode::try_from(2), Ok(FailureMode::Panic));
+        assert_eq!(FailureMode::try_from(3), Err(Error::InvalidFailureMode(3)));
+        assert_eq!(
+            FailureMode::try_from(u32::MAX),
+            Err(Error::InvalidFailureMode(u32::MAX))
+        );
+    }
+
+    #[test]
+    fn failure_mode_round_trip() {
+        for mode in
+            [FailureMode::Silent, FailureMode::Printk, FailureMode::Panic]
+        {
+            assert_eq!(FailureMode::try_from(u32::from(mode)), Ok(mode));
+        }
+    }
+
+    #[test]
+    fn decode_feature_bitmap() {
+        use AuditFeature::*;
+        let all = [
+            BacklogLimit,
+            BacklogWaitTime,
+            ExecutablePath,
+            ExcludeExtend,
+            SessionidFilter,
+            LostReset,
+            FilterFs,
+        ];
+
+        // Each feature has its own bit, in the kernel's order
+        for (bit, feature) in all.iter().enumerate() {
+            assert_eq!(u32::from(*feature), 1 << bit);
+        }
+
+        // Bitmap of a 4.x kernel, without lost reset and the fs filter
+        let mut status = StatusMessage::new();
+        status.feature_bitmap = 0x1f;
+        for feature in all {
+            let expected = !matches!(feature, LostReset | FilterFs);
+            assert_eq!(status.supports(feature), expected, "{:?}", feature);
+        }
+        assert!(status.features().contains(
+            AUDIT_FEATURE_BITMAP_BACKLOG_LIMIT
+                | AUDIT_FEATURE_BITMAP_EXECUTABLE_PATH
+        ));
+        assert!(!status.features().contains(
+            AUDIT_FEATURE_BITMAP_BACKLOG_LIMIT | AUDIT_FEATURE_BITMAP_FILTER_FS
+        ));
+        assert!(!FeatureBitmap::default().supports(BacklogLimit));
+    }
+}
@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: MIT
+
+//! Syscall name to number mapping, for the architectures used in
+//! `RuleField::Arch`
+
+mod aarch64;
+mod i386;
+mod x86_64;
+
+use crate::packet::constants::{
+    AUDIT_ARCH_AARCH64, AUDIT_ARCH_I386, AUDIT_ARCH_X86_64,
+};
+
+fn table(arch: u32) -> Option<&'static [(&'static str, u32)]> {
+    match arch {
+        AUDIT_ARCH_X86_64 => Some(x86_64::SYSCALLS),
+        AUDIT_ARCH_AARCH64 => Some(aarch64::SYSCALLS),
+        AUDIT_ARCH_I386 => Some(i386::SYSCALLS),
+        _ => None,
+    }
+}
+
+/// Return the number of the syscall `name` on the given `AUDIT_ARCH_*`
+/// architecture, or `None` if the syscall or the architecture is unknown
+pub fn resolve(arch: u32, name: &str) -> Option<u32> {
+    table(arch)?
+        .iter()
+        .find(|(syscall, _)| *syscall == name)
+        .map(|(_, nr)| *nr)
+}
+
+/// Return the name of the syscall number `nr` on the given `AUDIT_ARCH_*`
+/// architecture, or `None` if the syscall or the architecture is unknown
+pub fn name(arch: u32, nr: u32) -> Option<&'static str> {
+    table(arch)?
+        .iter()
+        .find(|(_, syscall)| *syscall == nr)
+        .map(|(name, _)| *name)
+}
@@ -0,0 +1,358 @@
+// SPDX-License-Identifier: MIT
+
+//! A scripted stand-in for the kernel, to exercise [`Handle`] without a
+//! privileged socket.
+//!
+//! [`MockKernel::connect`] returns a connection whose socket never reaches
+//! the kernel: the requests are recorded, and the replies are produced by a
+//! closure. Unsolicited messages, such as audit events, can be injected with
+//! [`MockKernel::send`].
+//!
+//! ```
+//! use audit::{
+//!     packet::{AuditMessage, StatusMessage},
+//!     testing::MockKernel,
+//! };
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), audit::Error> {
+//! let kernel = MockKernel::new(|request| match request.payload {
+//!     audit::NetlinkPayload::InnerMessage(AuditMessage::GetStatus(None)) => {
+//!         let mut status = StatusMessage::new();
+//!         status.enabled = 1;
+//!         vec![MockKernel::reply(
+//!             request,
+//!             AuditMessage::GetStatus(Some(status)),
+//!         )]
+//!     }
+//!     _ => vec![MockKernel::ack(request)],
+//! });
+//! let (connection, mut handle, _) = kernel.connect()?;
+//! tokio::spawn(connection);
+//!
+//! assert_eq!(handle.get_status().await?.enabled, 1);
+//! assert_eq!(kernel.requests().len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt, io,
+    num::NonZeroI32,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use futures::channel::mpsc::UnboundedReceiver;
+use netlink_packet_core::{
+    DoneMessage, ErrorMessage, NetlinkBuffer, NetlinkHeader, NetlinkMessage,
+    NetlinkPayload, NLM_F_DUMP, NLM_F_MULTIPART,
+};
+use netlink_packet_utils::Emitable;
+use netlink_sys::Socket;
+
+use crate::{
+    packet::{rules::RuleMessage, AuditMessage},
+    proto,
+    rules::without_classes,
+    sys::{self, protocols::NETLINK_AUDIT, SocketAddr},
+    AuditCodec, Handle,
+};
+
+type Responder =
+    Box<dyn FnMut(&NetlinkMessage<AuditMessage>) -> Vec<Reply> + Send>;
+type Reply = NetlinkMessage<AuditMessage>;
+
+struct State {
+    responder: Responder,
+    requests: Vec<NetlinkMessage<AuditMessage>>,
+    pending: VecDeque<io::Result<Vec<u8>>>,
+    waker: Option<Waker>,
+}
+
+impl State {
+    fn push(&mut self, datagram: io::Result<Vec<u8>>) {
+        self.pending.push_back(datagram);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A fake audit subsystem answering the requests of [`MockSocket`]s.
+/// Clones share the same state.
+#[derive(Clone)]
+pub struct MockKernel {
+    state: Arc<Mutex<State>>,
+}
+
+impl fmt::Debug for MockKernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockKernel").finish_non_exhaustive()
+    }
+}
+
+impl MockKernel {
+    /// Create a kernel that calls `responder` for each request, and sends
+    /// back the messages it returns, in order. See [`MockKernel::reply`],
+    /// [`MockKernel::ack`], [`MockKernel::error`] and [`MockKernel::done`]
+    /// to build them.
+    pub fn new<F>(responder: F) -> Self
+    where
+        F: FnMut(&NetlinkMessage<AuditMessage>) -> Vec<Reply> + Send + 'static,
+    {
+        MockKernel {
+            state: Arc::new(Mutex::new(State {
+                responder: Box::new(responder),
+                requests: Vec::new(),
+                pending: VecDeque::new(),
+                waker: None,
+            })),
+        }
+    }
+
+    /// Create a connection to this kernel, like
+    /// [`new_connection`](crate::new_connection). The connection future does
+    /// not depend on any runtime.
+    #[allow(clippy::type_complexity)]
+    pub fn connect(
+        &self,
+    ) -> io::Result<(
+        proto::Connection<AuditMessage, MockSocket, AuditCodec>,
+        Handle,
+        UnboundedReceiver<(NetlinkMessage<AuditMessage>, SocketAddr)>,
+    )> {
+        let socket = MockSocket {
+            socket: Socket::new(NETLINK_AUDIT)?,
+            state: self.state.clone(),
+        };
+        let (conn, handle, messages) =
+            netlink_proto::from_socket_with_codec(socket);
+        Ok((conn, Handle::new(handle), messages))
+    }
+
+    /// Return the requests received so far, in order
+    pub fn requests(&self) -> Vec<NetlinkMessage<AuditMessage>> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Send a message that is not a reply, such as an audit event. Use a
+    /// sequence number of 0 for the message to be unsolicited.
+    pub fn send(&self, mut message: NetlinkMessage<AuditMessage>) {
+        message.finalize();
+        let mut datagram = vec![0; message.buffer_len()];
+        message.serialize(&mut datagram);
+        self.state.lock().unwrap().push(Ok(datagram));
+    }
+
+    /// Make the next read from the socket fail. `ENOBUFS` is reported as an
+    /// overrun, other errors close the connection.
+    ///
+    /// ```
+    /// use audit::{
+    ///     packet::{constants::AUDIT_SYSCALL, AuditMessage},
+    ///     testing::MockKernel,
+    ///     Error, NetlinkMessage,
+    /// };
+    /// use futures::stream::StreamExt;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Error> {
+    /// let kernel = MockKernel::new(|request| vec![MockKernel::ack(request)]);
+    /// let (connection, handle, messages) = kernel.connect()?;
+    /// let mut handle = handle.with_messages(messages);
+    /// tokio::spawn(connection);
+    ///
+    /// kernel.fail_recv(std::io::Error::from_raw_os_error(libc::ENOBUFS));
+    /// kernel.send(NetlinkMessage::from(AuditMessage::Event((
+    ///     AUDIT_SYSCALL,
+    ///     "audit(1699999999.123:4567): syscall=59".into(),
+    /// ))));
+    ///
+    /// let mut events = handle.events()?;
+    /// assert_eq!(events.next().await, Some(Err(Error::Overrun)));
+    /// assert_eq!(events.next().await.unwrap()?.serial, 4567);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fail_recv(&self, error: io::Error) {
+        self.state.lock().unwrap().push(Err(error));
+    }
+
+    /// Build a reply to `request`. Like the kernel does, replies to dump
+    /// requests are flagged as part of a multipart message: end them with
+    /// [`MockKernel::done`].
+    pub fn reply(
+        request: &NetlinkMessage<AuditMessage>,
+        message: AuditMessage,
+    ) -> Reply {
+        Self::response(request, NetlinkPayload::InnerMessage(message))
+    }
+
+    /// Build an ack of `request`
+    pub fn ack(request: &NetlinkMessage<AuditMessage>) -> Reply {
+        Self::error(request, 0)
+    }
+
+    /// Build an error reply to `request`, with a positive errno such as
+    /// `libc::EPERM`
+    pub fn error(request: &NetlinkMessage<AuditMessage>, errno: i32) -> Reply {
+        let mut err_msg = ErrorMessage::default();
+        err_msg.code = NonZeroI32::new(-errno);
+        let mut header = vec![0; request.header.buffer_len()];
+        request.header.emit(&mut header);
+        err_msg.header = header;
+        Self::response(request, NetlinkPayload::Error(err_msg))
+    }
+
+    /// Return `rule` as the kernel stores it, and lists it back: the kernel
+    /// replaces the syscall class bits with the syscalls of the classes, and
+    /// clears them. Responders that keep the rules they are sent should
+    /// store this rather than the rule as sent.
+    pub fn stored_rule(rule: &RuleMessage) -> RuleMessage {
+        let mut rule = rule.clone();
+        rule.syscalls = without_classes(&rule.syscalls);
+        rule
+    }
+
+    /// Build the `NLMSG_DONE` message that ends the reply to a dump request
+    pub fn done(request: &NetlinkMessage<AuditMessage>) -> Reply {
+        Self::response(request, NetlinkPayload::Done(DoneMessage::default()))
+    }
+
+    fn response(
+        request: &NetlinkMessage<AuditMessage>,
+        payload: NetlinkPayload<AuditMessage>,
+    ) -> Reply {
+        let mut header = NetlinkHeader::default();
+        header.sequence_number = request.header.sequence_number;
+        header.port_number = request.header.port_number;
+        if request.header.flags & NLM_F_DUMP == NLM_F_DUMP {
+            header.flags = NLM_F_MULTIPART;
+        }
+        NetlinkMessage::new(header, payload)
+    }
+
+    fn handle_datagram(&self, mut datagram: &[u8]) -> io::Result<()> {
+        while !datagram.is_empty() {
+            let len = NetlinkBuffer::new_checked(datagram)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                .length() as usize;
+            let request = NetlinkMessage::deserialize(&datagram[..len])
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let mut state = self.state.lock().unwrap();
+            let replies = (state.responder)(&request);
+            state.requests.push(request);
+            drop(state);
+            for reply in replies {
+                self.send(reply);
+            }
+            datagram = &datagram[len..];
+        }
+        Ok(())
+    }
+}
+
+/// The socket of the connections returned by [`MockKernel::connect`].
+///
+/// It holds an unbound netlink socket, so that socket options can be set,
+/// but datagrams are exchanged with the [`MockKernel`] only.
+/// [`sys::AsyncSocket::new`] is not supported, since the socket would not
+/// be connected to any kernel.
+pub struct MockSocket {
+    socket: Socket,
+    state: Arc<Mutex<State>>,
+}
+
+impl fmt::Debug for MockSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockSocket")
+            .field("socket", &self.socket)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MockSocket {
+    fn kernel(&self) -> MockKernel {
+        MockKernel {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl sys::AsyncSocket for MockSocket {
+    fn socket_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    fn socket_mut(&mut self) -> &mut Socket {
+        &mut self.socket
+    }
+
+    fn new(_protocol: isize) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "use MockKernel::connect to create a MockSocket",
+        ))
+    }
+
+    fn poll_send(
+        &self,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.kernel().handle_datagram(buf).map(|()| buf.len()))
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        _addr: &SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        self.poll_send(cx, buf)
+    }
+
+    fn poll_recv<B>(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut B,
+    ) -> Poll<io::Result<()>>
+    where
+        B: bytes::BufMut,
+    {
+        self.poll_recv_from(cx, buf).map_ok(|_| ())
+    }
+
+    fn poll_recv_from<B>(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut B,
+    ) -> Poll<io::Result<SocketAddr>>
+    where
+        B: bytes::BufMut,
+    {
+        self.poll_recv_from_full(cx).map_ok(|(datagram, addr)| {
+            buf.put_slice(&datagram);
+            addr
+        })
+    }
+
+    fn poll_recv_from_full(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(Vec<u8>, SocketAddr)>> {
+        let mut state = self.state.lock().unwrap();
+        match state.pending.pop_front() {
+            // Messages come from the kernel, whose address is 0
+            Some(datagram) => {
+                Poll::Ready(datagram.map(|d| (d, SocketAddr::new(0, 0))))
+            }
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
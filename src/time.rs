@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MIT
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::stream::{Stream, StreamExt};
+
+use crate::Error;
+
+pub(crate) type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Sleep for the given duration, using the timer of the runtime matching the
+/// enabled socket feature.
+#[cfg(feature = "tokio_socket")]
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+    Box::pin(tokio::time::sleep(duration))
+}
+
+#[cfg(all(feature = "smol_socket", not(feature = "tokio_socket")))]
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+    Box::pin(async move {
+        async_io::Timer::after(duration).await;
+    })
+}
+
+/// Without any runtime, there is no timer: the returned future never
+/// completes.
+#[cfg(not(any(feature = "tokio_socket", feature = "smol_socket")))]
+pub(crate) fn sleep(_duration: Duration) -> Sleep {
+    Box::pin(futures::future::pending())
+}
+
+/// Stream wrapper yielding `Error::Timeout` and ending if the inner stream
+/// does not produce an item for `timeout`. Without timeout, it just wraps
+/// the items in `Ok`.
+pub(crate) struct TimeoutStream<S> {
+    stream: S,
+    timeout: Option<Duration>,
+    sleep: Option<Sleep>,
+    done: bool,
+}
+
+impl<S> TimeoutStream<S> {
+    pub(crate) fn new(stream: S, timeout: Option<Duration>) -> Self {
+        TimeoutStream {
+            stream,
+            timeout,
+            sleep: None,
+            done: false,
+        }
+    }
+}
+
+impl<S> Stream for TimeoutStream<S>
+where
+    S: Stream + Unpin,
+{
+    type Item = Result<S::Item, Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(item) = this.stream.poll_next_unpin(cx) {
+            // The timer restarts for the next item
+            this.sleep = None;
+            this.done = item.is_none();
+            return Poll::Ready(item.map(Ok));
+        }
+
+        if let Some(timeout) = this.timeout {
+            let timer = this.sleep.get_or_insert_with(|| sleep(timeout));
+            if timer.as_mut().poll(cx).is_ready() {
+                this.done = true;
+                return Poll::Ready(Some(Err(Error::Timeout)));
+            }
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: MIT
+
+use std::convert::TryInto;
+
+const TTY_STATUS_LEN: usize = 8;
+
+/// TTY input auditing status of the calling process, as exchanged with
+/// `AUDIT_TTY_GET` and `AUDIT_TTY_SET` messages (`struct audit_tty_status`)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct TtyStatus {
+    pub enabled: bool,
+    /// Whether input typed with echo disabled (passwords) is audited too
+    pub log_passwd: bool,
+}
+
+impl TtyStatus {
+    pub(crate) fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(TTY_STATUS_LEN);
+        bytes.extend_from_slice(&u32::from(self.enabled).to_ne_bytes());
+        bytes.extend_from_slice(&u32::from(self.log_passwd).to_ne_bytes());
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < TTY_STATUS_LEN {
+            return None;
+        }
+        let read = |i: usize| {
+            u32::from_ne_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap())
+        };
+        Some(TtyStatus {
+            enabled: read(0) != 0,
+            log_passwd: read(1) != 0,
+        })
+    }
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: MIT
+
+use std::convert::TryFrom;
+
+use crate::{
+    packet::{
+        constants::{
+            AUDIT_FIRST_USER_MSG, AUDIT_FIRST_USER_MSG2, AUDIT_LAST_USER_MSG,
+            AUDIT_LAST_USER_MSG2,
+        },
+        AuditMessage,
+    },
+    Error,
+};
+
+/// A user space record, such as `AUDIT_USER_AVC`, of any type in the
+/// `AUDIT_FIRST_USER_MSG..=AUDIT_LAST_USER_MSG` or
+/// `AUDIT_FIRST_USER_MSG2..=AUDIT_LAST_USER_MSG2` range.
+///
+/// netlink-packet-audit has no dedicated variant for these records: they
+/// are carried by `AuditMessage::Other` or `AuditMessage::Event`, whose
+/// payload is a string. Converting a record into an [`AuditMessage`] thus
+/// fails with [`Error::UnsupportedPayload`] if `data` is not valid UTF-8.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UserRecord {
+    pub msg_type: u16,
+    pub data: Vec<u8>,
+}
+
+impl UserRecord {
+    /// Return [`Error::InvalidUserMessageType`] if `msg_type` is not a user
+    /// message type
+    pub fn new(msg_type: u16, data: Vec<u8>) -> Result<Self, Error> {
+        if !is_user_message_type(msg_type) {
+            return Err(Error::InvalidUserMessageType(msg_type));
+        }
+        Ok(UserRecord { msg_type, data })
+    }
+}
+
+impl TryFrom<UserRecord> for AuditMessage {
+    type Error = Error;
+
+    fn try_from(record: UserRecord) -> Result<Self, Error> {
+        let UserRecord { msg_type, data } = record;
+        if !is_user_message_type(msg_type) {
+            return Err(Error::InvalidUserMessageType(msg_type));
+        }
+        let data = String::from_utf8(data)
+            .map_err(|_| Error::UnsupportedPayload(msg_type))?;
+        Ok(AuditMessage::Other((msg_type, data)))
+    }
+}
+
+impl TryFrom<&AuditMessage> for UserRecord {
+    type Error = Error;
+
+    /// Return [`Error::InvalidUserMessageType`] if the message is not a
+    /// user space record
+    fn try_from(message: &AuditMessage) -> Result<Self, Error> {
+        let msg_type = message.message_type();
+        match message {
+            AuditMessage::Event((_, data)) | AuditMessage::Other((_, data))
+                if is_user_message_type(msg_type) =>
+            {
+                Ok(UserRecord {
+                    msg_type,
+                    data: data.clone().into_bytes(),
+                })
+            }
+            _ => Err(Error::InvalidUserMessageType(msg_type)),
+        }
+    }
+}
+
+pub(crate) fn is_user_message_type(msg_type: u16) -> bool {
+    (AUDIT_FIRST_USER_MSG..=AUDIT_LAST_USER_MSG).contains(&msg_type)
+        || (AUDIT_FIRST_USER_MSG2..=AUDIT_LAST_USER_MSG2).contains(&msg_type)
+}
@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: MIT
+
+//! Round trips of rules through the kernel: each rule is added, listed
+//! back, compared with the rule that was sent, and deleted. This catches
+//! rules that the kernel stores differently from how they are encoded, and
+//! documents which fields survive the round trip.
+//!
+//! These tests change the audit configuration of the host and need the
+//! `CAP_AUDIT_CONTROL` capability: run them as root with
+//! `cargo test --features integration --test kernel`.
+
+#![cfg(feature = "integration")]
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use audit::{
+    current_arch, new_connection,
+    packet::{
+        rules::{RuleAction, RuleField, RuleFieldFlags, RuleFlags},
+        RuleMessage,
+    },
+    parse_rule, Handle, RuleBuilder, RuleMessageExt,
+};
+use futures::stream::TryStreamExt;
+
+fn connect() -> Handle {
+    let (connection, handle, _) = new_connection().unwrap();
+    tokio::spawn(connection);
+    handle.with_timeout(Duration::from_secs(5))
+}
+
+/// Add `rule`, check that the kernel lists it back unchanged, and delete
+/// it. Each test uses its own key, since the tests run concurrently.
+async fn roundtrip(handle: &mut Handle, key: &str, rule: RuleMessage) {
+    assert_eq!(rule.keys(), [key], "rules must have the key of the test");
+    handle.add_rule(rule.clone()).await.unwrap();
+    let listed: Result<Vec<RuleMessage>, _> =
+        handle.list_rules_by_key(key).try_collect().await;
+    // Delete the rule even if listing or the comparison fails
+    let deleted = handle.del_rule(rule.clone()).await;
+    let listed = listed.unwrap();
+    deleted.unwrap();
+
+    // The kernel clears the syscall class bits of the rules it stores, so
+    // the syscalls are compared with is_equivalent
+    let displayed: Vec<String> =
+        listed.iter().map(|l| l.display().to_string()).collect();
+    let same = |l: &RuleMessage| {
+        l.flags == rule.flags
+            && l.action == rule.action
+            && l.fields == rule.fields
+            && l.is_equivalent(&rule)
+    };
+    assert!(
+        listed.len() == 1 && same(&listed[0]),
+        "{} was listed back as {:?}",
+        rule.display(),
+        displayed
+    );
+}
+
+#[tokio::test]
+async fn watches() {
+    const KEY: &str = "audit-rs-test-watches";
+    let mut handle = connect();
+    for text in [
+        format!("-w /etc/passwd -p wa -k {KEY}"),
+        format!("-w /etc -p w -k {KEY}"),
+        format!("-w /etc/shadow -p rwxa -F auid>=1000 -k {KEY}"),
+    ] {
+        roundtrip(&mut handle, KEY, parse_rule(&text).unwrap()).await;
+    }
+}
+
+#[tokio::test]
+async fn syscall_rules() {
+    const KEY: &str = "audit-rs-test-syscalls";
+    let mut handle = connect();
+    for text in [
+        format!(
+            "-a always,exit -F arch=b64 -S openat -F exit=-EACCES -k {KEY}"
+        ),
+        format!(
+            "-a always,exit -F arch=b64 -S chmod,fchmod,fchmodat \
+             -F auid>=1000 -F auid!=unset -k {KEY}"
+        ),
+        format!("-a never,exit -F arch=b64 -S all -F uid=0 -F key={KEY}"),
+        format!("-a always,exit -F arch=b32 -S adjtimex,settimeofday -k {KEY}"),
+    ] {
+        roundtrip(&mut handle, KEY, parse_rule(&text).unwrap()).await;
+    }
+
+    let rule = RuleBuilder::new()
+        .filter(RuleFlags::FilterExit)
+        .action(RuleAction::Always)
+        .arch(current_arch())
+        .syscall("openat")
+        // -EACCES, the kernel stores the exit code as an unsigned value
+        .field(RuleField::Exit(-13i32 as u32), RuleFieldFlags::Equal)
+        .field(RuleField::Uid(1000), RuleFieldFlags::GreaterThanOrEqual)
+        .key(KEY)
+        .build()
+        .unwrap();
+    roundtrip(&mut handle, KEY, rule).await;
+}
+
+#[tokio::test]
+async fn several_keys() {
+    const KEY: &str = "audit-rs-test-keys";
+    let mut handle = connect();
+    let rule =
+        parse_rule(&format!("-w /etc/group -p wa -k {KEY} -k other")).unwrap();
+    handle.add_rule(rule.clone()).await.unwrap();
+    let listed: Result<Vec<RuleMessage>, _> =
+        handle.list_rules_by_key("other").try_collect().await;
+    handle.del_rule(rule.clone()).await.unwrap();
+    let listed = listed.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].fields, rule.fields);
+    assert!(listed[0].is_equivalent(&rule));
+}
+
+/// A watch on a path of almost 4 KiB, which makes the rule larger than a
+/// page
+#[tokio::test]
+async fn long_watch() {
+    const KEY: &str = "audit-rs-test-long-watch";
+    let mut handle = connect();
+
+    // The kernel only watches files whose parent directory exists
+    let root = std::env::temp_dir().join(KEY);
+    let mut dir: PathBuf = root.clone();
+    while dir.as_os_str().len() < 3800 {
+        dir.push("d".repeat(200));
+    }
+    fs::create_dir_all(&dir).unwrap();
+    let rule = parse_rule(&format!("-w {}/file -p wa -k {KEY}", dir.display()))
+        .unwrap();
+    roundtrip(&mut handle, KEY, rule).await;
+    fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,565 @@
+// SPDX-License-Identifier: MIT
+
+//! Tests of `Handle` against the scripted kernel of `audit::testing`
+
+#![cfg(feature = "testing")]
+
+use std::{
+    convert::TryInto,
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::stream::StreamExt;
+use netlink_packet_core::NETLINK_HEADER_LEN;
+
+use audit::{
+    packet::{
+        constants::{AUDIT_MAKE_EQUIV, AUDIT_SYSCALL},
+        rules::RuleMessage,
+        AuditMessage, StatusMessage,
+    },
+    parse_rule,
+    testing::MockKernel,
+    Error, FailureMode, Handle, NetlinkMessage, NetlinkPayload, RuleMessageExt,
+    AUDIT_STATUS_BACKLOG_LIMIT, AUDIT_STATUS_FAILURE,
+};
+
+type Rules = Arc<Mutex<Vec<RuleMessage>>>;
+
+/// A kernel that keeps the rules it is sent, like the audit subsystem
+/// does, without their syscall class bits. `fail` is called before each request is handled, and returns the
+/// errno to reply with instead, if any.
+fn rules_kernel<F>(rules: Rules, mut fail: F) -> Handle
+where
+    F: FnMut(&AuditMessage) -> Option<i32> + Send + 'static,
+{
+    let kernel = MockKernel::new(move |request| {
+        let message = match &request.payload {
+            NetlinkPayload::InnerMessage(message) => message,
+            _ => return vec![],
+        };
+        if let Some(errno) = fail(message) {
+            return vec![MockKernel::error(request, errno)];
+        }
+        let mut rules = rules.lock().unwrap();
+        match message {
+            AuditMessage::AddRule(rule) => {
+                if rules.iter().any(|r| r.is_equivalent(rule)) {
+                    return vec![MockKernel::error(request, libc::EEXIST)];
+                }
+                rules.push(MockKernel::stored_rule(rule));
+                vec![MockKernel::ack(request)]
+            }
+            AuditMessage::DelRule(rule) => {
+                match rules.iter().position(|r| r.is_equivalent(rule)) {
+                    Some(index) => {
+                        rules.remove(index);
+                        vec![MockKernel::ack(request)]
+                    }
+                    None => vec![MockKernel::error(request, libc::ENOENT)],
+                }
+            }
+            AuditMessage::ListRules(None) => rules
+                .iter()
+                .map(|rule| {
+                    MockKernel::reply(
+                        request,
+                        AuditMessage::ListRules(Some(rule.clone())),
+                    )
+                })
+                .chain(std::iter::once(MockKernel::done(request)))
+                .collect(),
+            _ => vec![MockKernel::ack(request)],
+        }
+    });
+    let (connection, handle, _) = kernel.connect().unwrap();
+    tokio::spawn(connection);
+    handle
+}
+
+fn rule(s: &str) -> RuleMessage {
+    parse_rule(s).unwrap()
+}
+
+/// Return the rule as the kernel lists it
+fn stored(s: &str) -> RuleMessage {
+    MockKernel::stored_rule(&rule(s))
+}
+
+fn has_key(message: &AuditMessage, key: &str) -> bool {
+    match message {
+        AuditMessage::AddRule(rule) => rule.keys().contains(&key),
+        _ => false,
+    }
+}
+
+fn displayed(rules: &Rules) -> Vec<String> {
+    let rules = rules.lock().unwrap();
+    rules.iter().map(|r| r.display().to_string()).collect()
+}
+
+#[tokio::test]
+async fn reload_rules_replaces_the_rules() {
+    let rules = Rules::default();
+    rules
+        .lock()
+        .unwrap()
+        .push(stored("-w /etc/passwd -p wa -k old"));
+    let mut handle = rules_kernel(rules.clone(), |_| None);
+
+    handle
+        .reload_rules(vec![
+            rule("-w /etc/shadow -p wa -k new"),
+            rule("-w /etc/group -p wa -k new"),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(
+        displayed(&rules),
+        ["-w /etc/shadow -p wa -k new", "-w /etc/group -p wa -k new"]
+    );
+}
+
+#[tokio::test]
+async fn reload_rules_restores_when_a_rule_fails() {
+    let rules = Rules::default();
+    rules
+        .lock()
+        .unwrap()
+        .push(stored("-w /etc/passwd -p wa -k old"));
+    let mut handle = rules_kernel(rules.clone(), |message| {
+        has_key(message, "broken").then_some(libc::EINVAL)
+    });
+
+    let result = handle
+        .reload_rules(vec![
+            rule("-w /etc/shadow -p wa -k new"),
+            rule("-w /etc/group -p wa -k broken"),
+        ])
+        .await;
+    match result {
+        Err(Error::RuleFailed { index: 1, error }) => {
+            assert_eq!(error.errno(), Some(libc::EINVAL))
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(displayed(&rules), ["-w /etc/passwd -p wa -k old"]);
+}
+
+#[tokio::test]
+async fn reload_rules_restores_when_the_cleanup_fails() {
+    let rules = Rules::default();
+    rules
+        .lock()
+        .unwrap()
+        .push(stored("-w /etc/passwd -p wa -k old"));
+    let mut lists = 0;
+    let mut handle = rules_kernel(rules.clone(), move |message| {
+        if let AuditMessage::ListRules(None) = message {
+            // Only the first listing, of the previous rules, succeeds
+            lists += 1;
+            return (lists > 1).then_some(libc::EPERM);
+        }
+        has_key(message, "broken").then_some(libc::EINVAL)
+    });
+
+    let result = handle
+        .reload_rules(vec![
+            rule("-w /etc/shadow -p wa -k new"),
+            rule("-w /etc/group -p wa -k broken"),
+        ])
+        .await;
+    // The error of the add is returned, along with the one of the cleanup
+    match result {
+        Err(Error::RollbackFailed { error, rollback }) => {
+            assert!(matches!(*error, Error::RuleFailed { index: 1, .. }));
+            assert_eq!(*rollback, Error::PermissionDenied);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    // The new rule could not be deleted, but the old one is back
+    assert_eq!(
+        displayed(&rules),
+        ["-w /etc/shadow -p wa -k new", "-w /etc/passwd -p wa -k old"]
+    );
+}
+
+#[tokio::test]
+async fn reload_rules_restores_rules_as_listed() {
+    // The kernel accepts this rule, but RuleMessageExt::validate does not
+    let old = "-a always,exit -F arch=b64 -S openat -F path=/etc/passwd -k old";
+    assert!(rule(old).validate().is_err());
+    let rules = Rules::default();
+    rules.lock().unwrap().push(stored(old));
+    let mut handle = rules_kernel(rules.clone(), |message| {
+        has_key(message, "broken").then_some(libc::EINVAL)
+    });
+
+    let result = handle
+        .reload_rules(vec![rule("-w /etc/group -p wa -k broken")])
+        .await;
+    assert!(
+        matches!(result, Err(Error::RuleFailed { index: 0, .. })),
+        "{:?}",
+        result
+    );
+    assert_eq!(*rules.lock().unwrap(), [stored(old)]);
+}
+
+#[tokio::test]
+async fn reload_rules_reports_failed_restores() {
+    let rules = Rules::default();
+    rules
+        .lock()
+        .unwrap()
+        .push(stored("-w /etc/passwd -p wa -k old"));
+    let mut handle = rules_kernel(rules.clone(), |message| {
+        if has_key(message, "old") {
+            return Some(libc::ENOMEM);
+        }
+        has_key(message, "broken").then_some(libc::EINVAL)
+    });
+
+    let result = handle
+        .reload_rules(vec![rule("-w /etc/group -p wa -k broken")])
+        .await;
+    match result {
+        Err(Error::RollbackFailed { error, rollback }) => {
+            assert!(matches!(*error, Error::RuleFailed { index: 0, .. }));
+            match *rollback {
+                Error::RuleFailed { index: 0, error } => {
+                    assert_eq!(error.errno(), Some(libc::ENOMEM))
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(rules.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn replace_rule_restores_the_old_rule() {
+    // The kernel accepts this rule, but RuleMessageExt::validate does not
+    let old = "-a always,exit -F arch=b64 -S openat -F path=/etc/passwd -k old";
+    let rules = Rules::default();
+    rules.lock().unwrap().push(stored(old));
+    let mut handle = rules_kernel(rules.clone(), |message| {
+        has_key(message, "broken").then_some(libc::EINVAL)
+    });
+
+    let listed = handle.list_rules_vec().await.unwrap().remove(0);
+    let result = handle
+        .replace_rule(listed.clone(), rule("-w /etc/group -p wa -k broken"))
+        .await;
+    assert_eq!(result.unwrap_err().errno(), Some(libc::EINVAL));
+    assert_eq!(
+        rules.lock().unwrap().as_slice(),
+        std::slice::from_ref(&listed)
+    );
+
+    handle
+        .replace_rule(listed, rule("-w /etc/group -p wa -k new"))
+        .await
+        .unwrap();
+    assert_eq!(displayed(&rules), ["-w /etc/group -p wa -k new"]);
+}
+
+#[tokio::test]
+async fn replace_rule_reports_failed_rollbacks() {
+    let rules = Rules::default();
+    rules
+        .lock()
+        .unwrap()
+        .push(stored("-w /etc/passwd -p wa -k old"));
+    let mut handle = rules_kernel(rules.clone(), |message| match message {
+        AuditMessage::AddRule(_) => Some(libc::ENOMEM),
+        _ => None,
+    });
+
+    let result = handle
+        .replace_rule(
+            stored("-w /etc/passwd -p wa -k old"),
+            rule("-w /etc/passwd -p rwa -k new"),
+        )
+        .await;
+    match result {
+        Err(Error::RollbackFailed { error, rollback }) => {
+            assert_eq!(error.errno(), Some(libc::ENOMEM));
+            assert_eq!(rollback.errno(), Some(libc::ENOMEM));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(rules.lock().unwrap().is_empty());
+}
+
+fn requests(kernel: &MockKernel) -> Vec<AuditMessage> {
+    kernel
+        .requests()
+        .into_iter()
+        .filter_map(|request| match request.payload {
+            NetlinkPayload::InnerMessage(message) => Some(message),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn add_and_delete_rules() {
+    let rules = Rules::default();
+    let mut handle = rules_kernel(rules.clone(), |_| None);
+    let passwd = rule("-w /etc/passwd -p wa -k identity");
+    let shadow = rule("-w /etc/shadow -p wa -k identity");
+
+    handle.add_rule(passwd.clone()).await.unwrap();
+    handle.add_rule(shadow.clone()).await.unwrap();
+    assert_eq!(
+        handle.add_rule(passwd.clone()).await,
+        Err(Error::RuleExists)
+    );
+    assert_eq!(
+        handle.list_rules_vec().await.unwrap(),
+        [
+            MockKernel::stored_rule(&passwd),
+            MockKernel::stored_rule(&shadow)
+        ]
+    );
+
+    handle.del_rule(passwd.clone()).await.unwrap();
+    let missing = handle.del_rule(passwd).await.unwrap_err();
+    assert_eq!(missing.errno(), Some(libc::ENOENT));
+    assert_eq!(handle.delete_all_rules().await, Ok(1));
+    assert!(rules.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn ensure_rules() {
+    let rules = Rules::default();
+    let mut handle = rules_kernel(rules.clone(), |_| None);
+    let passwd = rule("-w /etc/passwd -p wa -k identity");
+
+    assert_eq!(handle.ensure_rule(passwd.clone()).await, Ok(true));
+    assert_eq!(handle.ensure_rule(passwd.clone()).await, Ok(false));
+    assert_eq!(rules.lock().unwrap().len(), 1);
+    assert_eq!(handle.ensure_absent(&passwd).await, Ok(true));
+    assert_eq!(handle.ensure_absent(&passwd).await, Ok(false));
+}
+
+#[tokio::test]
+async fn update_watch_perms() {
+    let rules = Rules::default();
+    rules
+        .lock()
+        .unwrap()
+        .push(stored("-w /etc/passwd -p wa -k identity"));
+    let mut handle = rules_kernel(rules.clone(), |_| None);
+
+    handle.update_watch_perms("/etc/passwd", 0xf).await.unwrap();
+    assert_eq!(displayed(&rules), ["-w /etc/passwd -k identity -p rwxa"]);
+    let missing = handle.update_watch_perms("/etc/shadow", 0xf).await;
+    assert!(matches!(missing, Err(Error::InvalidRule(_))));
+}
+
+#[tokio::test]
+async fn invalid_rules_are_not_sent() {
+    let kernel = MockKernel::new(|request| vec![MockKernel::ack(request)]);
+    let (connection, mut handle, _) = kernel.connect().unwrap();
+    tokio::spawn(connection);
+
+    // Watches only support the exit filter list
+    let mut watch = rule("-w /etc/passwd -p wa");
+    watch.flags = audit::packet::rules::RuleFlags::FilterTask;
+    assert!(matches!(
+        handle.add_rule(watch).await,
+        Err(Error::InvalidRule(_))
+    ));
+    assert!(kernel.requests().is_empty());
+}
+
+#[tokio::test]
+async fn get_and_set_status() {
+    let kernel = MockKernel::new(|request| match &request.payload {
+        NetlinkPayload::InnerMessage(AuditMessage::GetStatus(None)) => {
+            let mut status = StatusMessage::new();
+            status.enabled = 1;
+            status.pid = 1234;
+            status.backlog_limit = 8192;
+            vec![MockKernel::reply(
+                request,
+                AuditMessage::GetStatus(Some(status)),
+            )]
+        }
+        NetlinkPayload::InnerMessage(AuditMessage::SetStatus(status))
+            if status.backlog_limit > 65536 =>
+        {
+            vec![MockKernel::error(request, libc::EPERM)]
+        }
+        _ => vec![MockKernel::ack(request)],
+    });
+    let (connection, mut handle, _) = kernel.connect().unwrap();
+    tokio::spawn(connection);
+
+    let status = handle.get_status().await.unwrap();
+    assert_eq!((status.enabled, status.pid), (1, 1234));
+    assert_eq!(handle.get_pid().await, Ok(1234));
+    handle.ping().await.unwrap();
+
+    handle.set_backlog_limit(16384).await.unwrap();
+    assert_eq!(
+        handle.set_backlog_limit(1 << 20).await,
+        Err(Error::PermissionDenied)
+    );
+    match &requests(&kernel)[3] {
+        AuditMessage::SetStatus(status) => {
+            assert_eq!(status.mask, AUDIT_STATUS_BACKLOG_LIMIT);
+            assert_eq!(status.backlog_limit, 16384);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn set_failure_mode() {
+    let kernel = MockKernel::new(|request| vec![MockKernel::ack(request)]);
+    let (connection, mut handle, _) = kernel.connect().unwrap();
+    tokio::spawn(connection);
+
+    let modes = [
+        (FailureMode::Silent, 0),
+        (FailureMode::Printk, 1),
+        (FailureMode::Panic, 2),
+    ];
+    for (mode, _) in modes {
+        handle.set_failure(mode).await.unwrap();
+    }
+    let requests = kernel.requests();
+    assert_eq!(requests.len(), modes.len());
+    for (request, (_, failure)) in requests.iter().zip(modes) {
+        let mut bytes = vec![0; request.buffer_len()];
+        request.serialize(&mut bytes);
+        // struct audit_status starts with mask, enabled and failure
+        let word = |index: usize| {
+            let offset = NETLINK_HEADER_LEN + index * 4;
+            u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+        };
+        assert_eq!(word(0), AUDIT_STATUS_FAILURE);
+        assert_eq!(word(1), 0);
+        assert_eq!(word(2), failure);
+    }
+}
+
+#[tokio::test]
+async fn make_equiv_path_lengths() {
+    let kernel = MockKernel::new(|request| vec![MockKernel::ack(request)]);
+    let (connection, mut handle, _) = kernel.connect().unwrap();
+    tokio::spawn(connection);
+
+    let path = |len: usize| format!("/{}", "p".repeat(len - 1));
+    handle.make_equiv(&path(127), "/mnt").await.unwrap();
+    // 256 bytes would encode as valid UTF-8, but is rejected like all the
+    // lengths of 128 bytes or more
+    for len in [128, 255, 256, 4095] {
+        assert_eq!(
+            handle.make_equiv("/mnt", &path(len)).await,
+            Err(Error::UnsupportedPayload(AUDIT_MAKE_EQUIV))
+        );
+    }
+    assert_eq!(kernel.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn registered_pid() {
+    // auditd is registered, and the kernel refuses to replace it
+    let kernel = MockKernel::new(|request| match &request.payload {
+        NetlinkPayload::InnerMessage(AuditMessage::GetStatus(None)) => {
+            let mut status = StatusMessage::new();
+            status.enabled = 1;
+            status.pid = 1234;
+            vec![MockKernel::reply(
+                request,
+                AuditMessage::GetStatus(Some(status)),
+            )]
+        }
+        NetlinkPayload::InnerMessage(AuditMessage::SetStatus(_)) => {
+            vec![MockKernel::error(request, libc::EEXIST)]
+        }
+        _ => vec![MockKernel::ack(request)],
+    });
+    let (connection, mut handle, _) = kernel.connect().unwrap();
+    tokio::spawn(connection);
+
+    for result in [
+        handle.set_pid(std::process::id()).await,
+        handle.enable_events().await,
+    ] {
+        match result {
+            Err(Error::NetlinkError(err)) => {
+                assert_eq!(err.code.map(|code| -code.get()), Some(libc::EEXIST))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+    assert_eq!(
+        handle.register_self().await.unwrap_err(),
+        Error::PidRegistered(1234)
+    );
+}
+
+#[tokio::test]
+async fn requests_time_out() {
+    let kernel = MockKernel::new(|_| vec![]);
+    let (connection, handle, _) = kernel.connect().unwrap();
+    tokio::spawn(connection);
+    let mut handle = handle.with_timeout(Duration::from_millis(10));
+
+    assert_eq!(handle.get_status().await, Err(Error::Timeout));
+    assert_eq!(handle.set_enabled(true).await, Err(Error::Timeout));
+}
+
+fn syscall_record(serial: u64) -> NetlinkMessage<AuditMessage> {
+    NetlinkMessage::from(AuditMessage::Event((
+        AUDIT_SYSCALL,
+        format!("audit(1699999999.123:{serial}): arch=c000003e syscall=59"),
+    )))
+}
+
+#[tokio::test]
+async fn overruns_are_reported_to_the_events() {
+    let kernel = MockKernel::new(|request| vec![MockKernel::ack(request)]);
+    let (connection, handle, messages) = kernel.connect().unwrap();
+    let mut handle = handle.with_messages(messages);
+    tokio::spawn(connection);
+    let mut events = handle.events().unwrap();
+
+    kernel.send(syscall_record(1));
+    kernel.fail_recv(io::Error::from_raw_os_error(libc::ENOBUFS));
+    kernel.fail_recv(io::Error::from_raw_os_error(libc::ENOBUFS));
+    kernel.send(syscall_record(2));
+
+    assert_eq!(events.next().await.unwrap().unwrap().serial, 1);
+    assert_eq!(events.next().await, Some(Err(Error::Overrun)));
+    assert_eq!(events.next().await, Some(Err(Error::Overrun)));
+    assert_eq!(events.next().await.unwrap().unwrap().serial, 2);
+
+    // The connection is still usable
+    handle.set_enabled(true).await.unwrap();
+}
+
+#[tokio::test]
+async fn overruns_are_reported_to_the_event_channel() {
+    let kernel = MockKernel::new(|request| vec![MockKernel::ack(request)]);
+    let (connection, handle, messages) = kernel.connect().unwrap();
+    let mut handle = handle.with_messages(messages);
+    tokio::spawn(connection);
+    let (forward, mut events, dropped) = handle.event_channel(8).unwrap();
+    tokio::spawn(forward);
+
+    kernel.fail_recv(io::Error::from_raw_os_error(libc::ENOBUFS));
+    kernel.send(syscall_record(3));
+
+    assert_eq!(events.next().await, Some(Err(Error::Overrun)));
+    assert_eq!(events.next().await.unwrap().unwrap().serial, 3);
+    assert_eq!(dropped.get(), 0);
+}